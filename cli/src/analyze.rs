@@ -0,0 +1,226 @@
+//! `imaged analyze`: statistics of one image, and of a pair compared with each other.
+//!
+//! Imaged containers are analyzed through the ciphertext they store, which needs no key.
+
+use core::analysis::{self, ChannelAnalysis, Differential, Similarity};
+use core::{CipherError, container};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{ColorType, DynamicImage};
+use serde_json::{Value, json};
+
+use crate::report;
+
+pub struct Subject {
+    pub path: PathBuf,
+    /// Method of an imaged container.
+    pub method: Option<String>,
+    pub image: DynamicImage,
+    pub channels: Vec<ChannelAnalysis>,
+}
+
+pub struct Comparison {
+    pub differential: Vec<Differential>,
+    pub similarity: Vec<Similarity>,
+}
+
+pub fn run(
+    image: &Path,
+    other: Option<&Path>,
+    json: bool,
+    report: Option<&Path>,
+) -> Result<(), CipherError> {
+    let subjects = [Some(image), other]
+        .into_iter()
+        .flatten()
+        .map(load)
+        .collect::<Result<Vec<_>, _>>()?;
+    let comparison = match subjects.as_slice() {
+        [a, b] => Some(compare(&a.image, &b.image)?),
+        _ => None,
+    };
+
+    if json {
+        let output = to_json(&subjects, comparison.as_ref());
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output).expect("analysis serializes to JSON")
+        );
+    } else {
+        print_tables(&subjects, comparison.as_ref());
+    }
+
+    if let Some(path) = report {
+        report::write(path, &subjects, comparison.as_ref())?;
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<Subject, CipherError> {
+    let bytes = fs::read(path)?;
+    let (image, method) = match container::ciphertext(&bytes) {
+        Ok((image, header)) => (image, Some(header.method)),
+        Err(CipherError::NotEncrypted) => (image::load_from_memory(&bytes)?, None),
+        Err(err) => return Err(err),
+    };
+
+    Ok(Subject {
+        path: path.to_path_buf(),
+        method,
+        channels: analysis::analyze(&image)?,
+        image,
+    })
+}
+
+/// Compares two images sample by sample, after bringing them to a common color type if
+/// they differ, such as a plaintext without alpha and its ciphertext.
+fn compare(a: &DynamicImage, b: &DynamicImage) -> Result<Comparison, CipherError> {
+    let is_float =
+        |image: &DynamicImage| matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+
+    let (a, b) = if a.color() == b.color() {
+        (a.clone(), b.clone())
+    } else if is_float(a) || is_float(b) {
+        (a.to_rgba32f().into(), b.to_rgba32f().into())
+    } else {
+        (a.to_rgba16().into(), b.to_rgba16().into())
+    };
+
+    Ok(Comparison {
+        differential: analysis::differential(&a, &b)?,
+        similarity: analysis::similarity(&a, &b)?,
+    })
+}
+
+pub const CHANNEL_HEADERS: [&str; 7] = [
+    "Channel",
+    "Entropy",
+    "Chi-square",
+    "Uniform",
+    "Corr. H",
+    "Corr. V",
+    "Corr. D",
+];
+
+pub fn channel_rows(channels: &[ChannelAnalysis]) -> Vec<Vec<String>> {
+    channels
+        .iter()
+        .map(|channel| {
+            vec![
+                channel.channel.to_string(),
+                format!("{:.4}", channel.entropy),
+                format!("{:.1}", channel.chi_square),
+                if channel.is_uniform() { "yes" } else { "no" }.to_string(),
+                format!("{:.4}", channel.correlation.horizontal),
+                format!("{:.4}", channel.correlation.vertical),
+                format!("{:.4}", channel.correlation.diagonal),
+            ]
+        })
+        .collect()
+}
+
+pub const COMPARISON_HEADERS: [&str; 5] = ["Channel", "NPCR %", "UACI %", "PSNR dB", "SSIM"];
+
+pub fn comparison_rows(comparison: &Comparison) -> Vec<Vec<String>> {
+    comparison
+        .differential
+        .iter()
+        .zip(&comparison.similarity)
+        .map(|(differential, similarity)| {
+            vec![
+                differential.channel.to_string(),
+                format!("{:.4}", differential.npcr),
+                format!("{:.4}", differential.uaci),
+                format!("{:.2}", similarity.psnr),
+                format!("{:.4}", similarity.ssim),
+            ]
+        })
+        .collect()
+}
+
+/// Describes an image in one line.
+pub fn title(subject: &Subject) -> String {
+    let mut title = format!(
+        "{} ({}x{} {:?}",
+        subject.path.display(),
+        subject.image.width(),
+        subject.image.height(),
+        subject.image.color()
+    );
+    if let Some(method) = &subject.method {
+        title.push_str(&format!(", encrypted with {method}"));
+    }
+    title.push(')');
+    title
+}
+
+fn print_tables(subjects: &[Subject], comparison: Option<&Comparison>) {
+    for subject in subjects {
+        println!("{}", title(subject));
+        print_table(&CHANNEL_HEADERS, &channel_rows(&subject.channels));
+        println!();
+    }
+
+    if let (Some(comparison), [a, b]) = (comparison, subjects) {
+        println!("{} vs {}", a.path.display(), b.path.display());
+        print_table(&COMPARISON_HEADERS, &comparison_rows(comparison));
+        println!(
+            "Ideal NPCR {:.4} %, UACI {:.4} % for 8-bit ciphertexts of plaintexts one sample apart",
+            analysis::IDEAL_NPCR,
+            analysis::IDEAL_UACI
+        );
+    }
+}
+
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |cells: Vec<&str>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:>width$}"))
+            .collect();
+        println!("  {}", cells.join("  "));
+    };
+
+    line(headers.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn to_json(subjects: &[Subject], comparison: Option<&Comparison>) -> Value {
+    let images: Vec<Value> = subjects
+        .iter()
+        .map(|subject| {
+            json!({
+                "path": subject.path.display().to_string(),
+                "method": subject.method,
+                "width": subject.image.width(),
+                "height": subject.image.height(),
+                "color_type": format!("{:?}", subject.image.color()),
+                "channels": subject.channels,
+            })
+        })
+        .collect();
+
+    json!({
+        "images": images,
+        "comparison": comparison.map(|comparison| json!({
+            "differential": comparison.differential,
+            "similarity": comparison.similarity,
+        })),
+    })
+}
@@ -0,0 +1,214 @@
+//! Directory and glob inputs.
+//!
+//! A batch mirrors every image below the input directory, or below the literal part of a
+//! glob pattern, into the output directory. Files are processed in parallel and each one
+//! gets a line in a JSON manifest, so a failed file does not stop the others.
+
+use core::CipherError;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, ValueEnum};
+use image::ImageFormat;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use serde_json::{Value, json};
+
+/// What happens to an input whose output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Existing {
+    Overwrite,
+    Skip,
+    Fail,
+}
+
+impl Existing {
+    /// Whether `output` should be written.
+    pub fn check(self, output: &Path) -> Result<bool, CipherError> {
+        if !output.exists() {
+            return Ok(true);
+        }
+
+        match self {
+            Existing::Overwrite => Ok(true),
+            Existing::Skip => Ok(false),
+            Existing::Fail => Err(CipherError::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", output.display()),
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// Also process the subdirectories of a directory input
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// What to do when an output file already exists
+    #[arg(long, value_enum, default_value_t = Existing::Overwrite)]
+    pub existing: Existing,
+    /// Where to write the JSON manifest of a batch [default: OUTPUT/manifest.json]
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+}
+
+pub struct Task {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+/// Whether `input` names several files rather than one.
+pub fn is_batch(input: &str) -> bool {
+    let path = Path::new(input);
+    path.is_dir() || (!path.is_file() && input.contains(['*', '?', '[']))
+}
+
+/// Lists the images matched by `input` together with their place under `output`, which
+/// `output_name` picks from the input path and the path relative to the batch root.
+pub fn collect(
+    input: &str,
+    output: &Path,
+    recursive: bool,
+    output_name: impl Fn(&Path, &Path) -> PathBuf,
+) -> Result<Vec<Task>, CipherError> {
+    let (root, pattern) = if Path::new(input).is_dir() {
+        let escaped = glob::Pattern::escape(input);
+        let pattern = if recursive {
+            format!("{escaped}/**/*")
+        } else {
+            format!("{escaped}/*")
+        };
+        (PathBuf::from(input), pattern)
+    } else {
+        (literal_prefix(input), input.to_string())
+    };
+
+    let paths = glob::glob(&pattern)
+        .map_err(|err| CipherError::InvalidParameter(format!("{input}: {err}")))?;
+
+    let mut tasks: Vec<Task> = Vec::new();
+    for path in paths {
+        let path = path.map_err(|err| CipherError::Io(err.into()))?;
+        // Earlier results are not inputs, even when the output is inside the input tree.
+        if !path.is_file() || path.starts_with(output) || ImageFormat::from_path(&path).is_err() {
+            continue;
+        }
+
+        let relative = path.strip_prefix(&root).unwrap_or(&path);
+        let task = Task {
+            output: output.join(output_name(&path, relative)),
+            input: path,
+        };
+
+        if let Some(other) = tasks.iter().find(|other| other.output == task.output) {
+            return Err(CipherError::InvalidParameter(format!(
+                "{} and {} would both be written to {}",
+                other.input.display(),
+                task.input.display(),
+                task.output.display()
+            )));
+        }
+        tasks.push(task);
+    }
+
+    if tasks.is_empty() {
+        return Err(CipherError::InvalidParameter(format!(
+            "{input} does not match any image"
+        )));
+    }
+    Ok(tasks)
+}
+
+/// Leading components of a glob pattern without wildcards, the root of the mirrored tree.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+/// Runs `process` on every task in parallel and writes the manifest. `job` describes the
+/// command in the manifest.
+pub fn run(
+    tasks: Vec<Task>,
+    output: &Path,
+    args: &BatchArgs,
+    job: Value,
+    process: impl Fn(&Path, &Path) -> Result<(), CipherError> + Sync,
+) -> Result<ExitCode, CipherError> {
+    let progress = ProgressBar::new(tasks.len() as u64).with_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} [{elapsed_precise}]")
+            .expect("progress template is valid"),
+    );
+
+    let files: Vec<Value> = tasks
+        .par_iter()
+        .map(|task| {
+            let result = args.existing.check(&task.output).and_then(|write| {
+                if !write {
+                    return Ok("skipped");
+                }
+                if let Some(parent) = task.output.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                process(&task.input, &task.output).map(|()| "done")
+            });
+            progress.inc(1);
+
+            let (status, error) = match result {
+                Ok(status) => (status, None),
+                Err(err) => {
+                    progress.println(format!("{}: {err}", task.input.display()));
+                    ("failed", Some(err.to_string()))
+                }
+            };
+            json!({
+                "input": task.input.display().to_string(),
+                "output": task.output.display().to_string(),
+                "status": status,
+                "error": error,
+            })
+        })
+        .collect();
+    progress.finish_and_clear();
+
+    let count = |status: &str| files.iter().filter(|file| file["status"] == status).count();
+    let (done, skipped, failed) = (count("done"), count("skipped"), count("failed"));
+
+    let manifest = match &args.manifest {
+        Some(manifest) => manifest.clone(),
+        None => {
+            fs::create_dir_all(output)?;
+            output.join("manifest.json")
+        }
+    };
+    let summary = json!({
+        "job": job,
+        "done": done,
+        "skipped": skipped,
+        "failed": failed,
+        "files": files,
+    });
+    fs::write(
+        &manifest,
+        serde_json::to_vec_pretty(&summary).expect("manifest serializes to JSON"),
+    )?;
+
+    eprintln!(
+        "{done} done, {skipped} skipped, {failed} failed, manifest in {}",
+        manifest.display()
+    );
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
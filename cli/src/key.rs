@@ -0,0 +1,75 @@
+//! Where the key comes from.
+//!
+//! Keys given on the command line end up in shell history and `ps` output, so by default
+//! the key is read from a file, an environment variable or a no-echo prompt.
+
+use core::CipherError;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::Args;
+use zeroize::Zeroizing;
+
+#[derive(Debug, Args)]
+pub struct KeyArgs {
+    /// Key as an argument, only accepted with --key-arg
+    #[arg(requires = "key_arg")]
+    key: Option<String>,
+    /// Allow the KEY argument, which is visible in shell history and `ps`
+    #[arg(long)]
+    key_arg: bool,
+    /// Read the key from the first line of a file
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["key", "key_env"])]
+    key_file: Option<PathBuf>,
+    /// Read the key from an environment variable
+    #[arg(long, value_name = "VAR", conflicts_with = "key")]
+    key_env: Option<String>,
+}
+
+impl KeyArgs {
+    /// Reads the key from the source that was given, or prompts for it on the terminal,
+    /// twice if `confirm` is set.
+    pub fn read(&mut self, confirm: bool) -> Result<Zeroizing<String>, CipherError> {
+        let key = if let Some(key) = self.key.take() {
+            Zeroizing::new(key)
+        } else if let Some(path) = &self.key_file {
+            let contents = Zeroizing::new(fs::read_to_string(path)?);
+            Zeroizing::new(contents.lines().next().unwrap_or_default().to_string())
+        } else if let Some(var) = &self.key_env {
+            Zeroizing::new(env::var(var).map_err(|_| {
+                CipherError::InvalidParameter(format!("environment variable {var} is not set"))
+            })?)
+        } else {
+            let key = prompt("Key: ")?;
+            if confirm {
+                let again = prompt("Confirm key: ")?;
+                if key != again {
+                    return Err(CipherError::InvalidParameter(
+                        "the keys do not match".to_string(),
+                    ));
+                }
+            }
+            key
+        };
+
+        if key.is_empty() {
+            return Err(CipherError::InvalidParameter(
+                "the key is empty".to_string(),
+            ));
+        }
+        Ok(key)
+    }
+}
+
+fn prompt(label: &str) -> Result<Zeroizing<String>, CipherError> {
+    rpassword::prompt_password(label)
+        .map(Zeroizing::new)
+        .map_err(|err| {
+            CipherError::Io(io::Error::new(
+                err.kind(),
+                format!("cannot prompt for the key ({err}), use --key-file or --key-env"),
+            ))
+        })
+}
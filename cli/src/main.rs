@@ -1,65 +1,358 @@
-use core::{ArnoldCat, HyperChaosSVD, ImageCipher};
-use std::path::Path;
+use core::container::AlphaMode;
+use core::registry::{self, MethodInfo, ParamKind};
+use core::rng::Generator;
+use core::{CipherError, ImageCipher, NonceMode, container, parallel};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::LazyLock;
 
-use clap::{Parser, Subcommand, ValueEnum, command};
-use image::codecs::png::PngEncoder;
+use clap::builder::PossibleValue;
+use clap::{Parser, Subcommand, ValueEnum};
+use image::ImageFormat;
+use serde_json::{Map, Value, json};
+
+use batch::BatchArgs;
+use key::KeyArgs;
+
+mod analyze;
+mod batch;
+mod key;
+mod report;
+mod sbox;
 
 #[derive(Debug, Parser)]
 #[command(name = "imaged")]
 #[command(about = "Multithreaded image encryption and decryption tool", long_about = None)]
 struct Cli {
+    /// Worker threads, one per CPU by default
+    #[arg(
+        short = 'j',
+        long,
+        global = true,
+        default_value_t = 0,
+        hide_default_value = true
+    )]
+    threads: usize,
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(ValueEnum, Clone, Debug)]
-enum CipherMethod {
-    ArnoldCat,
-    HenonMap,
-    HyperChaosSVD,
+/// A method from the core registry, so the accepted names always match the library.
+#[derive(Clone, Copy, Debug)]
+struct Method(&'static MethodInfo);
+
+static METHODS: LazyLock<Vec<Method>> =
+    LazyLock::new(|| registry::methods().iter().map(Method).collect());
+
+impl ValueEnum for Method {
+    fn value_variants<'a>() -> &'a [Self] {
+        &METHODS
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.0.name).help(self.0.title))
+    }
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
     #[command(arg_required_else_help = true)]
     Encrypt {
-        method: CipherMethod,
-        image_path: String,
-        output_path: String,
-        key: String,
+        method: Method,
+        /// Image file, directory or glob pattern
+        input: String,
+        /// Output file, or the output directory for a directory or pattern input. Encrypted
+        /// files are PNG images, so their extension is always `.png`
+        output: String,
+        #[command(flatten)]
+        key: KeyArgs,
+        /// Method parameter as `name=value`, see `imaged methods`
+        #[arg(short, long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+        /// JSON object of method parameters, such as the stages of `pipeline`. `--param`
+        /// values take precedence
+        #[arg(long, value_name = "PATH")]
+        params_file: Option<PathBuf>,
+        /// Nonce mixed into the cipher parameters
+        #[arg(long, value_enum, default_value_t)]
+        nonce: NonceMode,
+        /// What to do with the alpha channel of images that have one
+        #[arg(long, value_enum, default_value_t)]
+        alpha: AlphaMode,
+        #[command(flatten)]
+        batch: BatchArgs,
     },
+    /// Decrypt a file written by `encrypt`, with the method recorded in it
+    #[command(arg_required_else_help = true)]
     Decrypt {
-        method: CipherMethod,
-        image_path: String,
-        output_path: String,
-        key: String,
+        /// Encrypted file, directory or glob pattern
+        input: String,
+        /// Output file, or the output directory for a directory or pattern input. The
+        /// extension picks the format, otherwise the source format is used
+        output: String,
+        #[command(flatten)]
+        key: KeyArgs,
+        /// Fail unless the file was encrypted with this method
+        #[arg(short, long)]
+        method: Option<Method>,
+        #[command(flatten)]
+        batch: BatchArgs,
+    },
+    /// Measure how well an image is encrypted, or compare two images
+    #[command(arg_required_else_help = true)]
+    Analyze {
+        /// Image or imaged file to analyze
+        image: PathBuf,
+        /// Second image to compare with, such as the plaintext or another ciphertext
+        other: Option<PathBuf>,
+        /// Print JSON instead of tables
+        #[arg(long)]
+        json: bool,
+        /// Also write a report with plots, as HTML (.html) or Markdown (.md)
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
     },
+    /// Measure the S-boxes a chaotic generator produces from random seeds
+    Sbox {
+        /// Generator the S-boxes are sorted from
+        #[arg(long, value_enum, default_value_t = Generator::Henon)]
+        generator: Generator,
+        /// Number of S-boxes
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the available methods and their parameters
+    Methods,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Cli::parse();
+    let result = parallel::with_threads(args.threads, || run(args)).and_then(|result| result);
 
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            exit_code(&err)
+        }
+    }
+}
+
+/// Exit status for a failure. Usage errors are reported by clap with status 2.
+fn exit_code(err: &CipherError) -> ExitCode {
+    match err {
+        CipherError::WrongKey => ExitCode::from(3),
+        CipherError::Tampered => ExitCode::from(4),
+        CipherError::NotEncrypted
+        | CipherError::InvalidHeader(_)
+        | CipherError::UnsupportedVersion(_) => ExitCode::from(5),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+fn run(args: Cli) -> Result<ExitCode, CipherError> {
     match args.command {
         Commands::Encrypt {
             method,
-            image_path,
-            output_path,
-            key,
+            input,
+            output,
+            mut key,
+            params,
+            params_file,
+            nonce,
+            alpha,
+            batch,
         } => {
-            let image = image::open(Path::new(&image_path)).unwrap();
-
-            let enc_image = match method {
-                CipherMethod::ArnoldCat => ArnoldCat::encrypt(image, key),
-                CipherMethod::HenonMap => image,
-                CipherMethod::HyperChaosSVD => HyperChaosSVD::encrypt(image, key),
+            let params = parse_params(method.0, params_file.as_deref(), &params)?;
+            let cipher = method.0.build(&params)?;
+            let key = key.read(true)?;
+            let encrypt = |input: &Path, output: &Path| {
+                encrypt_file(cipher.as_ref(), input, output, &key, nonce, alpha)
             };
-            enc_image.save(Path::new(&output_path)).unwrap();
+
+            if batch::is_batch(&input) {
+                let output = Path::new(&output);
+                let tasks = batch::collect(&input, output, batch.recursive, |_, relative| {
+                    relative.with_extension("png")
+                })?;
+                let job = json!({
+                    "command": "encrypt",
+                    "method": cipher.name(),
+                    "params": cipher.params(),
+                    "nonce": nonce,
+                    "alpha": alpha,
+                });
+                return batch::run(tasks, output, &batch, job, encrypt);
+            }
+
+            let output = Path::new(&output).with_extension("png");
+            if batch.existing.check(&output)? {
+                encrypt(Path::new(&input), &output)?;
+            }
         }
         Commands::Decrypt {
+            input,
+            output,
+            mut key,
             method,
-            image_path,
-            output_path,
-            key,
-        } => {}
+            batch,
+        } => {
+            let key = key.read(false)?;
+            let decrypt = |input: &Path, output: &Path| decrypt_file(method, input, output, &key);
+
+            if batch::is_batch(&input) {
+                let output = Path::new(&output);
+                let tasks = batch::collect(&input, output, batch.recursive, |path, relative| {
+                    relative.with_extension(source_extension(path))
+                })?;
+                let job = json!({
+                    "command": "decrypt",
+                    "method": method.map(|method| method.0.name),
+                });
+                return batch::run(tasks, output, &batch, job, decrypt);
+            }
+
+            if batch.existing.check(Path::new(&output))? {
+                decrypt(Path::new(&input), Path::new(&output))?;
+            }
+        }
+        Commands::Analyze {
+            image,
+            other,
+            json,
+            report,
+        } => analyze::run(&image, other.as_deref(), json, report.as_deref())?,
+        Commands::Sbox {
+            generator,
+            count,
+            json,
+        } => sbox::run(generator, count, json)?,
+        Commands::Methods => print_methods(),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn encrypt_file(
+    cipher: &dyn ImageCipher,
+    input: &Path,
+    output: &Path,
+    key: &str,
+    nonce_mode: NonceMode,
+    alpha_mode: AlphaMode,
+) -> Result<(), CipherError> {
+    let image = image::open(input)?;
+    let source_format = ImageFormat::from_path(input).ok();
+
+    let enc_image = container::encrypt(image, source_format, cipher, key, nonce_mode, alpha_mode)?;
+    std::fs::write(output, enc_image)?;
+    Ok(())
+}
+
+fn decrypt_file(
+    method: Option<Method>,
+    input: &Path,
+    output: &Path,
+    key: &str,
+) -> Result<(), CipherError> {
+    let bytes = std::fs::read(input)?;
+
+    if let Some(method) = method {
+        let header = container::read_header(&bytes)?;
+        if header.method != method.0.name {
+            return Err(CipherError::InvalidParameter(format!(
+                "{} was encrypted with {}, not {}",
+                input.display(),
+                header.method,
+                method.0.name
+            )));
+        }
+    }
+
+    let (image, header) = container::decrypt(&bytes, key)?;
+    let format = ImageFormat::from_path(output)
+        .ok()
+        .or_else(|| {
+            header
+                .source_format
+                .as_deref()
+                .and_then(ImageFormat::from_extension)
+        })
+        .unwrap_or(ImageFormat::Png);
+    image.save_with_format(output, format)?;
+    Ok(())
+}
+
+/// Extension of the file an encrypted image was made from, `png` if unknown.
+fn source_extension(path: &Path) -> String {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| container::read_header(&bytes).ok())
+        .and_then(|header| header.source_format)
+        .unwrap_or_else(|| "png".to_string())
+}
+
+/// Turns the parameters file and `name=value` arguments into the JSON object the registry
+/// builds ciphers from.
+fn parse_params(
+    method: &MethodInfo,
+    file: Option<&Path>,
+    args: &[String],
+) -> Result<Value, CipherError> {
+    let mut params = match file {
+        Some(path) => match serde_json::from_slice(&std::fs::read(path)?) {
+            Ok(Value::Object(params)) => params,
+            Ok(_) => {
+                return Err(CipherError::InvalidParameter(format!(
+                    "{} does not hold a JSON object",
+                    path.display()
+                )));
+            }
+            Err(err) => {
+                return Err(CipherError::InvalidParameter(format!(
+                    "{}: {err}",
+                    path.display()
+                )));
+            }
+        },
+        None => Map::new(),
+    };
+
+    for arg in args {
+        let (name, value) = arg.split_once('=').ok_or_else(|| {
+            CipherError::InvalidParameter(format!("{arg} is not of the form name=value"))
+        })?;
+        let spec = method
+            .params
+            .iter()
+            .find(|spec| spec.name == name)
+            .ok_or_else(|| {
+                CipherError::InvalidParameter(format!("{} has no parameter {name}", method.name))
+            })?;
+
+        params.insert(name.to_string(), spec.parse(value)?);
+    }
+
+    Ok(Value::Object(params))
+}
+
+fn print_methods() {
+    for method in registry::methods() {
+        println!("{}: {}", method.name, method.description);
+
+        for spec in method.params {
+            let range = match spec.kind {
+                ParamKind::Integer { min, max, default } => {
+                    format!("{min}..={max}, default {default}")
+                }
+                ParamKind::Float { min, max, default } => {
+                    format!("{min}..={max}, default {default}")
+                }
+            };
+            println!("    {} ({range}): {}", spec.name, spec.description);
+        }
     }
 }
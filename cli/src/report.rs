@@ -0,0 +1,316 @@
+//! HTML and Markdown reports of `imaged analyze`.
+//!
+//! Plots are drawn as SVG and embedded as data URIs, so a report is a single file that can
+//! be attached to a write-up as it is.
+
+use core::CipherError;
+use core::analysis::{self, ChannelAnalysis, Direction, Neighbours};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::analyze::{self, Comparison, Subject};
+
+/// Most neighbour pairs drawn per channel in a scatter plot.
+const SCATTER_POINTS: usize = 2000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Html,
+    Markdown,
+}
+
+/// Writes the report to `path`, as HTML or Markdown depending on its extension.
+pub fn write(
+    path: &Path,
+    subjects: &[Subject],
+    comparison: Option<&Comparison>,
+) -> Result<(), CipherError> {
+    let format = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html" | "htm") => Format::Html,
+        Some("md" | "markdown") => Format::Markdown,
+        _ => {
+            return Err(CipherError::InvalidParameter(format!(
+                "{} is neither .html nor .md",
+                path.display()
+            )));
+        }
+    };
+
+    let mut document = Document::new(format);
+    document.heading(1, "Image analysis");
+
+    for subject in subjects {
+        document.heading(2, &analyze::title(subject));
+        document.table(
+            &analyze::CHANNEL_HEADERS,
+            &analyze::channel_rows(&subject.channels),
+        );
+        document.image("Histogram", &histogram_plot(&subject.channels));
+
+        for direction in Direction::ALL {
+            let neighbours = analysis::neighbours(&subject.image, direction, SCATTER_POINTS)?;
+            document.image(
+                &format!("{direction:?} correlation"),
+                &scatter_plot(direction, &neighbours),
+            );
+        }
+    }
+
+    if let (Some(comparison), [a, b]) = (comparison, subjects) {
+        document.heading(2, &format!("{} vs {}", a.path.display(), b.path.display()));
+        document.table(
+            &analyze::COMPARISON_HEADERS,
+            &analyze::comparison_rows(comparison),
+        );
+        document.paragraph(&format!(
+            "Ideal NPCR {:.4} %, UACI {:.4} % for 8-bit ciphertexts of plaintexts one sample \
+             apart.",
+            analysis::IDEAL_NPCR,
+            analysis::IDEAL_UACI
+        ));
+    }
+
+    fs::write(path, document.finish())?;
+    Ok(())
+}
+
+struct Document {
+    format: Format,
+    body: String,
+}
+
+impl Document {
+    fn new(format: Format) -> Self {
+        Self {
+            format,
+            body: String::new(),
+        }
+    }
+
+    fn heading(&mut self, level: usize, text: &str) {
+        match self.format {
+            Format::Html => writeln!(self.body, "<h{level}>{}</h{level}>", escape(text)),
+            Format::Markdown => writeln!(
+                self.body,
+                "{} {}\n",
+                "#".repeat(level),
+                escape_markdown(text)
+            ),
+        }
+        .expect("writing to a String cannot fail");
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        match self.format {
+            Format::Html => writeln!(self.body, "<p>{}</p>", escape(text)),
+            Format::Markdown => writeln!(self.body, "{}\n", escape_markdown(text)),
+        }
+        .expect("writing to a String cannot fail");
+    }
+
+    fn table(&mut self, headers: &[&str], rows: &[Vec<String>]) {
+        let body = &mut self.body;
+        match self.format {
+            Format::Html => {
+                body.push_str("<table>\n<tr>");
+                for header in headers {
+                    body.push_str(&format!("<th>{}</th>", escape(header)));
+                }
+                body.push_str("</tr>\n");
+                for row in rows {
+                    body.push_str("<tr>");
+                    for cell in row {
+                        body.push_str(&format!("<td>{}</td>", escape(cell)));
+                    }
+                    body.push_str("</tr>\n");
+                }
+                body.push_str("</table>\n");
+            }
+            Format::Markdown => {
+                let row = |cells: &mut dyn Iterator<Item = &str>| {
+                    let cells: Vec<String> = cells.map(escape_markdown).collect();
+                    format!("| {} |\n", cells.join(" | "))
+                };
+                body.push_str(&row(&mut headers.iter().copied()));
+                body.push_str(&format!("|{}\n", " --: |".repeat(headers.len())));
+                for cells in rows {
+                    body.push_str(&row(&mut cells.iter().map(String::as_str)));
+                }
+                body.push('\n');
+            }
+        }
+    }
+
+    fn image(&mut self, alt: &str, svg: &str) {
+        let uri = format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg));
+        match self.format {
+            Format::Html => writeln!(self.body, "<img alt=\"{}\" src=\"{uri}\">", escape(alt)),
+            Format::Markdown => writeln!(self.body, "![{}]({uri})\n", escape_markdown(alt)),
+        }
+        .expect("writing to a String cannot fail");
+    }
+
+    fn finish(self) -> String {
+        match self.format {
+            Format::Html => format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                 <title>Image analysis</title>\n<style>\n\
+                 body {{ font-family: sans-serif; margin: 2em; }}\n\
+                 table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+                 th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: right; }}\n\
+                 img {{ margin: 0.5em; vertical-align: top; }}\n\
+                 </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+                self.body
+            ),
+            Format::Markdown => self.body,
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Backslash-escapes the characters that Markdown would read as markup, such as a `|` in
+/// a file name, which would otherwise end a table cell.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if "\\`*_[]<>#|".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+fn color(channel: &str) -> &'static str {
+    match channel {
+        "R" => "#d62728",
+        "G" => "#2ca02c",
+        "B" => "#1f77b4",
+        "A" => "#7f7f7f",
+        _ => "#333333",
+    }
+}
+
+/// Plot area of every chart, with a margin for the axis labels.
+const PLOT: f64 = 256.0;
+const MARGIN: f64 = 24.0;
+
+fn frame(width: f64, title: &str, x_label: &str, y_label: &str, content: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{total_width}\" \
+         height=\"{total_height}\" font-family=\"sans-serif\" font-size=\"10\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\
+         <text x=\"{MARGIN}\" y=\"14\" font-size=\"12\">{title}</text>\
+         <g transform=\"translate({MARGIN} {MARGIN})\">\
+         <rect width=\"{width}\" height=\"{PLOT}\" fill=\"none\" stroke=\"#999\"/>{content}</g>\
+         <text x=\"{MARGIN}\" y=\"{label_y}\">{x_label}</text>\
+         <text x=\"4\" y=\"{MARGIN}\" writing-mode=\"tb\">{y_label}</text></svg>",
+        total_width = width + 2.0 * MARGIN,
+        total_height = PLOT + 2.0 * MARGIN + 4.0,
+        label_y = PLOT + MARGIN + 14.0,
+    )
+}
+
+fn histogram_plot(channels: &[ChannelAnalysis]) -> String {
+    let width = 2.0 * PLOT;
+    let peak = channels
+        .iter()
+        .flat_map(|channel| channel.histogram.iter().copied())
+        .max()
+        .unwrap_or(1)
+        .max(1) as f64;
+
+    let mut content = String::new();
+    for channel in channels {
+        let points: Vec<String> = channel
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(level, &count)| {
+                format!(
+                    "{:.1},{:.1}",
+                    level as f64 * width / (analysis::LEVELS - 1) as f64,
+                    PLOT - count as f64 / peak * PLOT
+                )
+            })
+            .collect();
+        content.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1\" points=\"{}\"/>",
+            color(channel.channel),
+            points.join(" ")
+        ));
+    }
+
+    frame(width, "Histogram", "level 0 to 255", "count", &content)
+}
+
+fn scatter_plot(direction: Direction, neighbours: &[Neighbours]) -> String {
+    let mut content = String::new();
+    for channel in neighbours {
+        // One path of small squares per channel keeps thousands of points compact.
+        let squares: String = channel
+            .pairs
+            .iter()
+            .map(|&(a, b)| format!("M{a} {}h2v2h-2z", 255 - b))
+            .collect();
+        content.push_str(&format!(
+            "<path fill=\"{}\" fill-opacity=\"0.4\" d=\"{squares}\"/>",
+            color(channel.channel)
+        ));
+    }
+
+    frame(
+        PLOT,
+        &format!("{direction:?} neighbours"),
+        "sample",
+        "neighbour",
+        &content,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_escapes_file_names() {
+        let mut document = Document::new(Format::Markdown);
+        document.heading(2, "a|b_*c*.png vs [d].png");
+        document.table(
+            &["File", "NPCR %"],
+            &[vec!["x|y.png".to_string(), "99.6".to_string()]],
+        );
+        let markdown = document.finish();
+
+        assert!(markdown.contains(r"## a\|b\_\*c\*.png vs \[d\].png"));
+        let rows: Vec<&str> = markdown
+            .lines()
+            .filter(|line| line.starts_with('|'))
+            .collect();
+        assert_eq!(rows[2], r"| x\|y.png | 99.6 |");
+        for row in rows {
+            assert_eq!(row.replace(r"\|", "").matches('|').count(), 3, "{row}");
+        }
+    }
+
+    #[test]
+    fn html_escapes_file_names() {
+        let mut document = Document::new(Format::Html);
+        document.heading(2, "<a&b>.png");
+        document.table(&["File"], &[vec!["\"x\".png".to_string()]]);
+        let html = document.finish();
+
+        assert!(html.contains("<h2>&lt;a&amp;b&gt;.png</h2>"));
+        assert!(html.contains("<td>&quot;x&quot;.png</td>"));
+    }
+}
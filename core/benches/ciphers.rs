@@ -0,0 +1,56 @@
+//! Encryption throughput of every registered method on one thread and on the whole machine.
+
+use core::parallel::with_threads;
+use core::{KdfParams, registry};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use image::{DynamicImage, Rgb, RgbImage};
+
+fn ciphers(c: &mut Criterion) {
+    let image: DynamicImage = RgbImage::from_fn(512, 512, |x, y| {
+        Rgb([x as u8, y as u8, (x * 7 + y * 13) as u8])
+    })
+    .into();
+    let kdf = KdfParams {
+        salt: [7; 16],
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let seed = kdf.derive("key").unwrap();
+    let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut thread_counts = vec![1, cpus];
+    thread_counts.dedup();
+
+    let mut group = c.benchmark_group("encrypt");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(image.as_bytes().len() as u64));
+
+    for info in registry::methods() {
+        let cipher = info.default_cipher();
+        for &threads in &thread_counts {
+            group.bench_with_input(
+                BenchmarkId::new(info.name, threads),
+                &threads,
+                |b, &threads| {
+                    // The pool is built once per batch, not once per iteration.
+                    b.iter_custom(|iterations| {
+                        with_threads(threads, || {
+                            let start = std::time::Instant::now();
+                            for _ in 0..iterations {
+                                cipher.encrypt(image.clone(), &seed).unwrap();
+                            }
+                            start.elapsed()
+                        })
+                        .unwrap()
+                    });
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, ciphers);
+criterion_main!(benches);
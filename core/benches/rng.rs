@@ -0,0 +1,42 @@
+//! Throughput of the chaotic generators on their own.
+
+use core::KdfParams;
+use core::rng::Generator;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const LEN: usize = 1 << 20;
+
+fn generators(c: &mut Criterion) {
+    let kdf = KdfParams {
+        salt: [7; 16],
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let seed = kdf.derive("key").unwrap();
+    let mut buffer = vec![0; LEN];
+
+    let mut group = c.benchmark_group("fill_bytes");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(LEN as u64));
+
+    for generator in [
+        Generator::Logistic,
+        Generator::Tent,
+        Generator::Pwlcm,
+        Generator::Henon,
+        Generator::Arnold,
+        Generator::Chen,
+    ] {
+        let mut rng = generator.rng(&seed, "bench");
+        group.bench_function(BenchmarkId::from_parameter(format!("{generator:?}")), |b| {
+            b.iter(|| rng.fill_bytes(&mut buffer));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, generators);
+criterion_main!(benches);
@@ -0,0 +1,569 @@
+//! Statistical measures used to judge how well a cipher hides its input.
+//!
+//! Every measure works on 256 levels per channel. 8-bit samples are used as they are,
+//! 16-bit samples keep their high byte and floating-point samples are mapped from
+//! `[0, 1]`, or from the range of the image when it leaves `[0, 1]`, as the ciphertexts of
+//! [`HyperChaosSVD`](crate::HyperChaosSVD) do. All channels of the image are analyzed,
+//! alpha included.
+
+use image::{ColorType, DynamicImage};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::CipherError;
+
+/// Number of levels a channel is quantized to.
+pub const LEVELS: usize = 256;
+/// Chi-square value of a uniform histogram at the 5% significance level, with 255 degrees
+/// of freedom.
+pub const CHI_SQUARE_CRITICAL: f64 = 293.2478;
+/// Expected NPCR in percent between two unrelated random 8-bit images.
+pub const IDEAL_NPCR: f64 = 99.6094;
+/// Expected UACI in percent between two unrelated random 8-bit images.
+pub const IDEAL_UACI: f64 = 33.4635;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelAnalysis {
+    /// Channel name, such as `R` or `A`.
+    pub channel: &'static str,
+    /// Number of samples at each level.
+    pub histogram: Vec<u64>,
+    /// Shannon entropy of the histogram in bits, at most 8.
+    pub entropy: f64,
+    /// Chi-square statistic of the histogram against a uniform one.
+    pub chi_square: f64,
+    pub correlation: Correlation,
+}
+
+impl ChannelAnalysis {
+    /// Whether the histogram passes the chi-square test for uniformity.
+    pub fn is_uniform(&self) -> bool {
+        self.chi_square < CHI_SQUARE_CRITICAL
+    }
+}
+
+/// Neighbour a sample is paired with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+    Diagonal,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 3] = [
+        Direction::Horizontal,
+        Direction::Vertical,
+        Direction::Diagonal,
+    ];
+
+    fn offset(self) -> (usize, usize) {
+        match self {
+            Direction::Horizontal => (1, 0),
+            Direction::Vertical => (0, 1),
+            Direction::Diagonal => (1, 1),
+        }
+    }
+}
+
+/// Pearson correlation coefficients between neighbouring samples, over every pair in the
+/// image. They are `NaN` for a channel without any variation.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Correlation {
+    pub horizontal: f64,
+    pub vertical: f64,
+    pub diagonal: f64,
+}
+
+/// Difference between two images of the same size, usually the ciphertexts of two
+/// plaintexts one sample apart.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Differential {
+    pub channel: &'static str,
+    /// Number of changing pixel rate: percentage of samples that differ.
+    pub npcr: f64,
+    /// Unified average changing intensity: mean absolute difference in percent of the
+    /// largest level.
+    pub uaci: f64,
+}
+
+/// How close two images of the same size are, usually a plaintext and its ciphertext or
+/// decryption.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Similarity {
+    pub channel: &'static str,
+    /// Peak signal-to-noise ratio in dB, infinite for identical channels.
+    pub psnr: f64,
+    /// Mean structural similarity index over 11x11 Gaussian windows with a standard
+    /// deviation of 1.5, or over the whole channel if it is smaller than that.
+    pub ssim: f64,
+}
+
+/// Pairs of a sample and its neighbour, the points of a correlation scatter plot.
+#[derive(Debug, Clone, Serialize)]
+pub struct Neighbours {
+    pub channel: &'static str,
+    pub pairs: Vec<(u8, u8)>,
+}
+
+/// One channel quantized to [`LEVELS`] levels.
+struct Plane {
+    name: &'static str,
+    width: usize,
+    height: usize,
+    levels: Vec<u8>,
+}
+
+impl Plane {
+    fn at(&self, x: usize, y: usize) -> u8 {
+        self.levels[y * self.width + x]
+    }
+}
+
+/// Histogram, entropy, uniformity and correlation of every channel of `image`.
+pub fn analyze(image: &DynamicImage) -> Result<Vec<ChannelAnalysis>, CipherError> {
+    let range = float_range(image);
+
+    Ok(planes(image, range)?
+        .par_iter()
+        .map(|plane| {
+            let histogram = histogram(&plane.levels);
+            ChannelAnalysis {
+                channel: plane.name,
+                entropy: entropy(&histogram),
+                chi_square: chi_square(&histogram),
+                correlation: Correlation {
+                    horizontal: correlation(plane, Direction::Horizontal),
+                    vertical: correlation(plane, Direction::Vertical),
+                    diagonal: correlation(plane, Direction::Diagonal),
+                },
+                histogram,
+            }
+        })
+        .collect())
+}
+
+/// NPCR and UACI of every channel between `a` and `b`, which need the same dimensions and
+/// color type.
+pub fn differential(a: &DynamicImage, b: &DynamicImage) -> Result<Vec<Differential>, CipherError> {
+    Ok(paired_planes(a, b)?
+        .into_iter()
+        .map(|(a, b)| {
+            let count = a.levels.len() as f64;
+            let changed = a
+                .levels
+                .iter()
+                .zip(&b.levels)
+                .filter(|(a, b)| a != b)
+                .count();
+            let distance: u64 = a
+                .levels
+                .iter()
+                .zip(&b.levels)
+                .map(|(a, b)| u64::from(a.abs_diff(*b)))
+                .sum();
+
+            Differential {
+                channel: a.name,
+                npcr: 100.0 * changed as f64 / count,
+                uaci: 100.0 * distance as f64 / (count * (LEVELS - 1) as f64),
+            }
+        })
+        .collect())
+}
+
+/// PSNR and SSIM of every channel between `a` and `b`, which need the same dimensions and
+/// color type.
+pub fn similarity(a: &DynamicImage, b: &DynamicImage) -> Result<Vec<Similarity>, CipherError> {
+    Ok(paired_planes(a, b)?
+        .par_iter()
+        .map(|(a, b)| {
+            let squared_error: u64 = a
+                .levels
+                .iter()
+                .zip(&b.levels)
+                .map(|(a, b)| u64::from(a.abs_diff(*b)).pow(2))
+                .sum();
+            let mse = squared_error as f64 / a.levels.len() as f64;
+            let peak = (LEVELS - 1) as f64;
+
+            Similarity {
+                channel: a.name,
+                psnr: 10.0 * (peak * peak / mse).log10(),
+                ssim: ssim(a, b),
+            }
+        })
+        .collect())
+}
+
+/// Up to `limit` evenly spread pairs of a sample and its neighbour in `direction`, for
+/// every channel of `image`.
+pub fn neighbours(
+    image: &DynamicImage,
+    direction: Direction,
+    limit: usize,
+) -> Result<Vec<Neighbours>, CipherError> {
+    let (dx, dy) = direction.offset();
+
+    Ok(planes(image, float_range(image))?
+        .into_iter()
+        .map(|plane| {
+            let (width, height) = (
+                plane.width.saturating_sub(dx),
+                plane.height.saturating_sub(dy),
+            );
+            let count = width * height;
+            let step = count.div_ceil(limit.max(1)).max(1);
+
+            let pairs = (0..count)
+                .step_by(step)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    (plane.at(x, y), plane.at(x + dx, y + dy))
+                })
+                .collect();
+            Neighbours {
+                channel: plane.name,
+                pairs,
+            }
+        })
+        .collect())
+}
+
+/// Quantizes two images the same way, after checking that they can be compared.
+fn paired_planes(a: &DynamicImage, b: &DynamicImage) -> Result<Vec<(Plane, Plane)>, CipherError> {
+    if (a.width(), a.height()) != (b.width(), b.height()) {
+        return Err(CipherError::InvalidDimensions {
+            width: b.width(),
+            height: b.height(),
+        });
+    }
+    if a.color() != b.color() {
+        return Err(CipherError::UnsupportedColorType(b.color()));
+    }
+
+    // Both sides need the same mapping to levels, or equal samples could differ.
+    let range = float_range(a)
+        .zip(float_range(b))
+        .map(|((a_min, a_max), (b_min, b_max))| (a_min.min(b_min), a_max.max(b_max)));
+
+    Ok(planes(a, range)?
+        .into_iter()
+        .zip(planes(b, range)?)
+        .collect())
+}
+
+pub fn histogram(levels: &[u8]) -> Vec<u64> {
+    let mut histogram = vec![0; LEVELS];
+    for &level in levels {
+        histogram[usize::from(level)] += 1;
+    }
+    histogram
+}
+
+/// Shannon entropy of a histogram in bits.
+pub fn entropy(histogram: &[u64]) -> f64 {
+    let total: u64 = histogram.iter().sum();
+
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// Chi-square statistic of a histogram against the uniform distribution over its bins.
+pub fn chi_square(histogram: &[u64]) -> f64 {
+    let total: u64 = histogram.iter().sum();
+    let expected = total as f64 / histogram.len() as f64;
+
+    histogram
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Correlation between every sample and its neighbour in `direction`. The sums are kept
+/// in integers, since they outgrow the precision of `f64` on large images.
+fn correlation(plane: &Plane, direction: Direction) -> f64 {
+    let (dx, dy) = direction.offset();
+    let (mut n, mut sum_a, mut sum_b) = (0u128, 0u128, 0u128);
+    let (mut sum_aa, mut sum_bb, mut sum_ab) = (0u128, 0u128, 0u128);
+
+    for y in 0..plane.height.saturating_sub(dy) {
+        for x in 0..plane.width.saturating_sub(dx) {
+            let a = u128::from(plane.at(x, y));
+            let b = u128::from(plane.at(x + dx, y + dy));
+            n += 1;
+            sum_a += a;
+            sum_b += b;
+            sum_aa += a * a;
+            sum_bb += b * b;
+            sum_ab += a * b;
+        }
+    }
+
+    let covariance = ((n * sum_ab) as i128 - (sum_a * sum_b) as i128) as f64;
+    let variance_a = (n * sum_aa - sum_a * sum_a) as f64;
+    let variance_b = (n * sum_bb - sum_b * sum_b) as f64;
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+/// Side of the SSIM window.
+const SSIM_WINDOW: usize = 11;
+/// Standard deviation of the SSIM window.
+const SSIM_SIGMA: f64 = 1.5;
+
+fn ssim(a: &Plane, b: &Plane) -> f64 {
+    let c1 = (0.01 * (LEVELS - 1) as f64).powi(2);
+    let c2 = (0.03 * (LEVELS - 1) as f64).powi(2);
+
+    let signals: Vec<Vec<f64>> = [
+        |a: f64, _: f64| a,
+        |_: f64, b: f64| b,
+        |a: f64, _: f64| a * a,
+        |_: f64, b: f64| b * b,
+        |a: f64, b: f64| a * b,
+    ]
+    .iter()
+    .map(|signal| {
+        a.levels
+            .iter()
+            .zip(&b.levels)
+            .map(|(&a, &b)| signal(f64::from(a), f64::from(b)))
+            .collect()
+    })
+    .collect();
+
+    let means: Vec<Vec<f64>> = if a.width < SSIM_WINDOW || a.height < SSIM_WINDOW {
+        signals
+            .iter()
+            .map(|signal| vec![signal.iter().sum::<f64>() / signal.len() as f64])
+            .collect()
+    } else {
+        let kernel = gaussian_kernel();
+        signals
+            .iter()
+            .map(|signal| blur(signal, a.width, a.height, &kernel))
+            .collect()
+    };
+
+    let local: Vec<f64> = (0..means[0].len())
+        .map(|i| {
+            let (mean_a, mean_b) = (means[0][i], means[1][i]);
+            let variance_a = means[2][i] - mean_a * mean_a;
+            let variance_b = means[3][i] - mean_b * mean_b;
+            let covariance = means[4][i] - mean_a * mean_b;
+
+            ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+                / ((mean_a * mean_a + mean_b * mean_b + c1) * (variance_a + variance_b + c2))
+        })
+        .collect();
+    local.iter().sum::<f64>() / local.len() as f64
+}
+
+fn gaussian_kernel() -> Vec<f64> {
+    let center = (SSIM_WINDOW / 2) as f64;
+    let weights: Vec<f64> = (0..SSIM_WINDOW)
+        .map(|i| (-(i as f64 - center).powi(2) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+/// Separable convolution of a `width x height` signal with `kernel`, keeping only the
+/// positions where the whole window fits.
+fn blur(signal: &[f64], width: usize, height: usize, kernel: &[f64]) -> Vec<f64> {
+    let span = kernel.len();
+    let (out_width, out_height) = (width - span + 1, height - span + 1);
+
+    let rows: Vec<f64> = (0..height)
+        .flat_map(|y| {
+            (0..out_width).map(move |x| {
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| weight * signal[y * width + x + k])
+                    .sum::<f64>()
+            })
+        })
+        .collect();
+
+    (0..out_height)
+        .flat_map(|y| {
+            let rows = &rows;
+            (0..out_width).map(move |x| {
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| weight * rows[(y + k) * out_width + x])
+                    .sum::<f64>()
+            })
+        })
+        .collect()
+}
+
+/// Sample range used to quantize a floating-point image, `None` for integer images.
+fn float_range(image: &DynamicImage) -> Option<(f32, f32)> {
+    let samples: &[f32] = match image {
+        DynamicImage::ImageRgb32F(buffer) => buffer.as_raw(),
+        DynamicImage::ImageRgba32F(buffer) => buffer.as_raw(),
+        _ => return None,
+    };
+
+    let (min, max) = samples
+        .iter()
+        .filter(|sample| sample.is_finite())
+        .fold((0.0f32, 1.0f32), |(min, max), &sample| {
+            (min.min(sample), max.max(sample))
+        });
+    Some((min, max))
+}
+
+/// Splits `image` into quantized channels. `range` maps floating-point samples.
+fn planes(image: &DynamicImage, range: Option<(f32, f32)>) -> Result<Vec<Plane>, CipherError> {
+    let names: &[&'static str] = match image.color() {
+        ColorType::L8 | ColorType::L16 => &["L"],
+        ColorType::La8 | ColorType::La16 => &["L", "A"],
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => &["R", "G", "B"],
+        ColorType::Rgba8 | ColorType::Rgba16 | ColorType::Rgba32F => &["R", "G", "B", "A"],
+        color_type => return Err(CipherError::UnsupportedColorType(color_type)),
+    };
+
+    let levels: Vec<u8> = match image {
+        DynamicImage::ImageRgb32F(buffer) => quantize(buffer.as_raw(), range),
+        DynamicImage::ImageRgba32F(buffer) => quantize(buffer.as_raw(), range),
+        _ => match image.as_flat_samples_u16() {
+            Some(samples) => samples
+                .samples
+                .iter()
+                .map(|&sample| (sample >> 8) as u8)
+                .collect(),
+            None => image.as_bytes().to_vec(),
+        },
+    };
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    Ok(names
+        .iter()
+        .enumerate()
+        .map(|(channel, &name)| Plane {
+            name,
+            width,
+            height,
+            levels: levels
+                .iter()
+                .skip(channel)
+                .step_by(names.len())
+                .copied()
+                .collect(),
+        })
+        .collect())
+}
+
+fn quantize(samples: &[f32], range: Option<(f32, f32)>) -> Vec<u8> {
+    let (min, max) = range.unwrap_or((0.0, 1.0));
+    let scale = if max > min { 255.0 / (max - min) } else { 0.0 };
+
+    samples
+        .iter()
+        .map(|&sample| ((sample - min) * scale).round().clamp(0.0, 255.0) as u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    fn gray(width: u32, height: u32, level: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        GrayImage::from_fn(width, height, |x, y| Luma([level(x, y)])).into()
+    }
+
+    #[test]
+    fn every_level_once_is_uniform() {
+        let levels: Vec<u8> = (0..=255).collect();
+        let histogram = histogram(&levels);
+
+        assert!(histogram.iter().all(|&count| count == 1));
+        assert!((entropy(&histogram) - 8.0).abs() < 1e-12);
+        assert_eq!(chi_square(&histogram), 0.0);
+    }
+
+    #[test]
+    fn constant_channel_has_no_entropy() {
+        let [channel] = analyze(&gray(16, 16, |_, _| 77))
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(channel.entropy, 0.0);
+        assert_eq!(channel.histogram[77], 256);
+        assert!(!channel.is_uniform());
+        assert!(channel.correlation.horizontal.is_nan());
+    }
+
+    #[test]
+    fn identical_images_do_not_differ() {
+        let image = gray(16, 16, |x, y| (x * 16 + y) as u8);
+        let [differential] = differential(&image, &image).unwrap().try_into().unwrap();
+
+        assert_eq!(differential.npcr, 0.0);
+        assert_eq!(differential.uaci, 0.0);
+    }
+
+    #[test]
+    fn opposite_images_differ_everywhere() {
+        let [differential] = differential(&gray(16, 16, |_, _| 0), &gray(16, 16, |_, _| 255))
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(differential.npcr, 100.0);
+        assert_eq!(differential.uaci, 100.0);
+    }
+
+    #[test]
+    fn gradient_is_perfectly_correlated() {
+        let [channel] = analyze(&gray(32, 32, |x, y| (x + y) as u8))
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let Correlation {
+            horizontal,
+            vertical,
+            diagonal,
+        } = channel.correlation;
+
+        for coefficient in [horizontal, vertical, diagonal] {
+            assert!((coefficient - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn identical_images_are_similar() {
+        let image = gray(32, 24, |x, y| (x * 7 + y * 3) as u8);
+        let [similarity] = similarity(&image, &image).unwrap().try_into().unwrap();
+
+        assert_eq!(similarity.psnr, f64::INFINITY);
+        assert!((similarity.ssim - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn psnr_follows_the_mean_squared_error() {
+        // Every sample is 10 off, so the MSE is 100.
+        let a = gray(16, 16, |x, y| (x + y * 16) as u8 / 2);
+        let b = gray(16, 16, |x, y| (x + y * 16) as u8 / 2 + 10);
+        let [similarity] = similarity(&a, &b).unwrap().try_into().unwrap();
+
+        let expected = 10.0 * (255.0f64 * 255.0 / 100.0).log10();
+        assert!((similarity.psnr - expected).abs() < 1e-9);
+        assert!((similarity.psnr - 28.1308).abs() < 1e-4);
+        assert!(similarity.ssim < 1.0);
+    }
+}
@@ -0,0 +1,310 @@
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::registry::{self, ParamKind, ParamSpec};
+use crate::rng::Orbit;
+use crate::samples::Samples;
+use crate::{CipherError, ImageCipher, Seed};
+
+/// Generalized Arnold cat map over the whole `width x height` image.
+///
+/// The cat matrix `[[1, a], [b, a*b + 1]]` is applied as its two shears: a round sends
+/// `(x, y)` to `x' = (x + a*y) mod width`, `y' = (y + b*x') mod height`. Each shear is a
+/// bijection on its own axis, so a round is a bijection of the rectangular torus for any
+/// dimensions, and on a square image it is exactly the classic map
+/// `(x + a*y, b*x + (a*b + 1)*y) mod n`. Decryption undoes the shears in reverse order
+/// the same number of times, so the dimensions are preserved.
+/// `a`, `b` and the iteration count, between 1 and `max_iterations`, are derived from the
+/// key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArnoldCat {
+    pub max_iterations: u32,
+}
+
+pub(crate) const DEFAULT_MAX_ITERATIONS: u32 = 16;
+
+pub(crate) const PARAMS: &[ParamSpec] = &[ParamSpec {
+    name: "max_iterations",
+    description: "Upper bound of the key-derived number of rounds",
+    kind: ParamKind::Integer {
+        min: 1,
+        max: 64,
+        default: DEFAULT_MAX_ITERATIONS as u64,
+    },
+}];
+
+/// Rejects a `max_iterations` outside [`PARAMS`]. Ciphers built directly instead of through
+/// the registry have not been checked, and 0 would divide by zero.
+pub(crate) fn check_max_iterations(max_iterations: u32) -> Result<(), CipherError> {
+    PARAMS[0].check(&serde_json::Value::from(max_iterations))
+}
+
+impl Default for ArnoldCat {
+    fn default() -> Self {
+        Self {
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+}
+
+struct CatParams {
+    a: u64,
+    b: u64,
+    iterations: u32,
+}
+
+impl Drop for CatParams {
+    fn drop(&mut self) {
+        self.a.zeroize();
+        self.b.zeroize();
+        self.iterations.zeroize();
+    }
+}
+
+impl CatParams {
+    fn from_seed(seed: &Seed, width: u32, height: u32, max_iterations: u32) -> Self {
+        let width = u64::from(width.max(2));
+        let height = u64::from(height.max(2));
+
+        Self {
+            a: 1 + seed.parameter("arnold.a") % (width - 1),
+            b: 1 + seed.parameter("arnold.b") % (height - 1),
+            iterations: 1
+                + (seed.parameter("arnold.iterations") % u64::from(max_iterations)) as u32,
+        }
+    }
+
+    fn forward(&self, x: u64, y: u64, width: u64, height: u64) -> (u64, u64) {
+        let nx = (x + self.a * y) % width;
+        let ny = (y + self.b * nx) % height;
+        (nx, ny)
+    }
+
+    fn inverse(&self, x: u64, y: u64, width: u64, height: u64) -> (u64, u64) {
+        let py = (y + height - self.b * x % height) % height;
+        let px = (x + width - self.a * py % width) % width;
+        (px, py)
+    }
+}
+
+/// Modulus of [`CatOrbit`], the Mersenne prime `2^61 - 1`.
+const ORBIT_MODULUS: u64 = (1 << 61) - 1;
+
+/// Orbit of the generalized cat map over `Z_p x Z_p` with `p = 2^61 - 1`, for
+/// [`ChaoticRng`](crate::rng::ChaoticRng).
+///
+/// A step applies the same two shears as the cipher, with key-derived `a`, `b` and
+/// starting point, and yields the top 32 of the 61 bits of `x`. The map is linear, so its
+/// outputs can be predicted from a handful of earlier ones.
+pub struct CatOrbit {
+    a: u64,
+    b: u64,
+    x: u64,
+    y: u64,
+}
+
+impl CatOrbit {
+    fn step(&mut self) {
+        let shear = |value: u64, factor: u64, other: u64| {
+            ((u128::from(value) + u128::from(factor) * u128::from(other))
+                % u128::from(ORBIT_MODULUS)) as u64
+        };
+
+        self.x = shear(self.x, self.a, self.y);
+        self.y = shear(self.y, self.b, self.x);
+    }
+}
+
+impl Orbit for CatOrbit {
+    const TRANSIENT: u32 = 64;
+
+    fn new(seed: &Seed, label: &str, transient: u32) -> Self {
+        let nonzero =
+            |name: &str| 1 + seed.parameter(&format!("{label}.{name}")) % (ORBIT_MODULUS - 1);
+        let mut orbit = Self {
+            a: nonzero("a"),
+            b: nonzero("b"),
+            // Any point but the fixed origin.
+            x: nonzero("x0"),
+            y: seed.parameter(&format!("{label}.y0")) % ORBIT_MODULUS,
+        };
+
+        for _ in 0..transient {
+            orbit.step();
+        }
+        orbit
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.step();
+        (self.x >> 29) as u32
+    }
+}
+
+impl Drop for CatOrbit {
+    fn drop(&mut self) {
+        self.a.zeroize();
+        self.b.zeroize();
+        self.x.zeroize();
+        self.y.zeroize();
+    }
+}
+
+/// Builds the samples whose pixel at `(x, y)` is the pixel of `samples` at `source`
+/// applied `iterations` times to `(x, y)`.
+///
+/// Each output pixel only reads the input, so rows are filled in parallel.
+fn gather(
+    samples: &Samples,
+    iterations: u32,
+    source: impl Fn(u64, u64) -> (u64, u64) + Sync,
+) -> Vec<u8> {
+    let width = samples.width as usize;
+    let pixel_len = samples.pixel_len();
+    let mut buffer = vec![0; samples.bytes.len()];
+
+    buffer
+        .par_chunks_mut(width * pixel_len)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(pixel_len).enumerate() {
+                let (mut sx, mut sy) = (x as u64, y as u64);
+                for _ in 0..iterations {
+                    (sx, sy) = source(sx, sy);
+                }
+
+                let start = (sy as usize * width + sx as usize) * pixel_len;
+                pixel.copy_from_slice(&samples.bytes[start..start + pixel_len]);
+            }
+        });
+
+    buffer
+}
+
+/// Moves every pixel of `samples` to its image under the map drawn from `seed`.
+pub(crate) fn permute(samples: &Samples, seed: &Seed, max_iterations: u32) -> Vec<u8> {
+    let (width, height) = (samples.width, samples.height);
+    let params = CatParams::from_seed(seed, width, height, max_iterations);
+
+    // Pixel (x, y) moves to forward(x, y), so it receives the pixel at inverse(x, y).
+    gather(samples, params.iterations, |x, y| {
+        params.inverse(x, y, u64::from(width), u64::from(height))
+    })
+}
+
+/// Reverses [`permute`].
+pub(crate) fn unpermute(samples: &Samples, seed: &Seed, max_iterations: u32) -> Vec<u8> {
+    let (width, height) = (samples.width, samples.height);
+    let params = CatParams::from_seed(seed, width, height, max_iterations);
+
+    gather(samples, params.iterations, |x, y| {
+        params.forward(x, y, u64::from(width), u64::from(height))
+    })
+}
+
+impl ImageCipher for ArnoldCat {
+    fn name(&self) -> &'static str {
+        "arnold-cat"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        check_max_iterations(self.max_iterations)?;
+        let mut samples = Samples::new(image)?;
+        samples.bytes = permute(&samples, seed, self.max_iterations);
+        Ok(samples.into_image())
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        check_max_iterations(self.max_iterations)?;
+        let mut samples = Samples::new(image)?;
+        samples.bytes = unpermute(&samples, seed, self.max_iterations);
+        Ok(samples.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::testing::{image, seed};
+
+    #[test]
+    fn decrypt_restores_the_image() {
+        let plaintext = image(40, 30);
+        let cipher = ArnoldCat::default();
+
+        let ciphertext = cipher.encrypt(plaintext.clone(), &seed(1)).unwrap();
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(cipher.decrypt(ciphertext, &seed(1)).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn map_is_a_bijection() {
+        let (width, height) = (40, 30);
+        let params = CatParams::from_seed(&seed(1), width, height, DEFAULT_MAX_ITERATIONS);
+        let (width, height) = (u64::from(width), u64::from(height));
+        let mut hit = vec![false; (width * height) as usize];
+
+        for y in 0..height {
+            for x in 0..width {
+                let (nx, ny) = params.forward(x, y, width, height);
+                assert!(!std::mem::replace(
+                    &mut hit[(ny * width + nx) as usize],
+                    true
+                ));
+                assert_eq!(params.inverse(nx, ny, width, height), (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn encrypt_moves_every_pixel_once() {
+        let plaintext = image(40, 30);
+        let ciphertext = ArnoldCat::default()
+            .encrypt(plaintext.clone(), &seed(1))
+            .unwrap();
+
+        let sorted = |image: &DynamicImage| {
+            let mut pixels: Vec<_> = image.as_bytes().chunks_exact(3).collect::<Vec<_>>();
+            pixels.sort();
+            pixels.concat()
+        };
+        assert_eq!(sorted(&ciphertext), sorted(&plaintext));
+    }
+
+    #[test]
+    fn decrypt_restores_degenerate_and_rectangular_images() {
+        let cipher = ArnoldCat::default();
+
+        for (width, height) in [(1, 1), (1, 17), (17, 1), (13, 9)] {
+            let plaintext = image(width, height);
+            let ciphertext = cipher.encrypt(plaintext.clone(), &seed(2)).unwrap();
+            assert_eq!(ciphertext.dimensions(), (width, height));
+            assert_eq!(cipher.decrypt(ciphertext, &seed(2)).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn out_of_range_iterations_are_rejected() {
+        for max_iterations in [0, 65] {
+            let cipher = ArnoldCat { max_iterations };
+
+            assert!(matches!(
+                cipher.encrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+            assert!(matches!(
+                cipher.decrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::arnold;
+use crate::samples::Samples;
+use crate::{CipherError, ImageCipher, Seed, registry};
+
+/// Three-dimensional cat map over the `width x height x channels` box of samples.
+///
+/// Unlike [`ArnoldCat`](crate::ArnoldCat), which moves whole pixels, every sample moves on
+/// its own, so the channels of a pixel end up in different pixels and channels and the
+/// correlation between R, G and B is broken up along with the spatial one. A round applies
+/// three shears, each a bijection on its own axis:
+/// `x' = (x + a1*y + a2*c) mod width`, `y' = (y + b1*x' + b2*c) mod height` and
+/// `c' = (c + s(x', y')) mod channels`, and decryption undoes them in reverse order the same
+/// number of times.
+///
+/// With at most four channels, a linear channel shear `c1*x' + c2*y'` would make the map
+/// closed modulo the channel count, and for some keys the iteration count is a multiple of
+/// its period there, which leaves every sample in its channel. The shift `s` is instead
+/// `h * channels / 2^64` for `h = (c1*x' + c2*y') mod 2^64`, the top two bits of `h` for
+/// RGBA, which depends on every bit of the coordinates. A grayscale image has a single
+/// channel, where the map reduces to the two spatial shears.
+/// The six coefficients and the iteration count, between 1 and `max_iterations`, are
+/// derived from the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CatMap3D {
+    pub max_iterations: u32,
+}
+
+impl Default for CatMap3D {
+    fn default() -> Self {
+        Self {
+            max_iterations: arnold::DEFAULT_MAX_ITERATIONS,
+        }
+    }
+}
+
+/// Key-derived 3-D cat map for one box, usable as a permutation stage on its own.
+pub(crate) struct Cat3DParams {
+    width: u64,
+    height: u64,
+    channels: u64,
+    a1: u64,
+    a2: u64,
+    b1: u64,
+    b2: u64,
+    c1: u64,
+    c2: u64,
+    iterations: u32,
+}
+
+impl Drop for Cat3DParams {
+    fn drop(&mut self) {
+        self.a1.zeroize();
+        self.a2.zeroize();
+        self.b1.zeroize();
+        self.b2.zeroize();
+        self.c1.zeroize();
+        self.c2.zeroize();
+        self.iterations.zeroize();
+    }
+}
+
+impl Cat3DParams {
+    /// Draws the coefficients for a `width x height` image with `channels` samples per
+    /// pixel from the `label.*` parameters
+    /// of `seed`. None of the spatial ones is a multiple of its modulus, so every shear
+    /// moves samples along its axis.
+    pub(crate) fn from_seed(
+        seed: &Seed,
+        label: &str,
+        width: u32,
+        height: u32,
+        channels: usize,
+        max_iterations: u32,
+    ) -> Self {
+        let width = u64::from(width);
+        let height = u64::from(height);
+        let coefficient = |name: &str, modulus: u64| {
+            1 + seed.parameter(&format!("{label}.{name}")) % (modulus.max(2) - 1)
+        };
+
+        Self {
+            width,
+            height,
+            channels: channels as u64,
+            a1: coefficient("a1", width),
+            a2: coefficient("a2", width),
+            b1: coefficient("b1", height),
+            b2: coefficient("b2", height),
+            c1: seed.parameter(&format!("{label}.c1")) | 1,
+            c2: seed.parameter(&format!("{label}.c2")) | 1,
+            iterations: 1
+                + (seed.parameter(&format!("{label}.iterations")) % u64::from(max_iterations))
+                    as u32,
+        }
+    }
+
+    /// Channel shift of the samples at `(x, y)`.
+    fn shift(&self, x: u64, y: u64) -> u64 {
+        let h = self
+            .c1
+            .wrapping_mul(x)
+            .wrapping_add(self.c2.wrapping_mul(y));
+        ((u128::from(h) * u128::from(self.channels)) >> 64) as u64
+    }
+
+    fn forward(&self, x: u64, y: u64, c: u64) -> (u64, u64, u64) {
+        let nx = (x + self.a1 * y + self.a2 * c) % self.width;
+        let ny = (y + self.b1 * nx + self.b2 * c) % self.height;
+        let nc = (c + self.shift(nx, ny)) % self.channels;
+        (nx, ny, nc)
+    }
+
+    fn inverse(&self, x: u64, y: u64, c: u64) -> (u64, u64, u64) {
+        let pc = (c + self.channels - self.shift(x, y)) % self.channels;
+        let py = (y + 2 * self.height - (self.b1 * x + self.b2 * pc) % self.height) % self.height;
+        let px = (x + 2 * self.width - (self.a1 * py + self.a2 * pc) % self.width) % self.width;
+        (px, py, pc)
+    }
+
+    /// Moves every sample of `samples` to its image under the map.
+    pub(crate) fn permute(&self, samples: &Samples) -> Vec<u8> {
+        // Sample (x, y, c) moves to forward(x, y, c), so it receives the one at inverse.
+        self.gather(samples, |x, y, c| self.inverse(x, y, c))
+    }
+
+    /// Reverses [`permute`](Self::permute).
+    pub(crate) fn unpermute(&self, samples: &Samples) -> Vec<u8> {
+        self.gather(samples, |x, y, c| self.forward(x, y, c))
+    }
+
+    /// Builds the samples whose value at `(x, y, c)` is the one of `samples` at `source`
+    /// applied `iterations` times. Each output sample only reads the input, so rows are
+    /// filled in parallel.
+    fn gather(
+        &self,
+        samples: &Samples,
+        source: impl Fn(u64, u64, u64) -> (u64, u64, u64) + Sync,
+    ) -> Vec<u8> {
+        let sample_len = samples.sample_len();
+        let mut buffer = vec![0; samples.bytes.len()];
+
+        buffer
+            .par_chunks_mut(samples.width as usize * samples.pixel_len())
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (i, sample) in row.chunks_exact_mut(sample_len).enumerate() {
+                    let (mut sx, mut sy, mut sc) =
+                        (i as u64 / self.channels, y as u64, i as u64 % self.channels);
+                    for _ in 0..self.iterations {
+                        (sx, sy, sc) = source(sx, sy, sc);
+                    }
+
+                    let start = ((sy * self.width + sx) * self.channels + sc) as usize * sample_len;
+                    sample.copy_from_slice(&samples.bytes[start..start + sample_len]);
+                }
+            });
+
+        buffer
+    }
+}
+
+impl ImageCipher for CatMap3D {
+    fn name(&self) -> &'static str {
+        "cat-map-3d"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        arnold::check_max_iterations(self.max_iterations)?;
+        let mut samples = Samples::new(image)?;
+        let params = Cat3DParams::from_seed(
+            seed,
+            "cat3d",
+            samples.width,
+            samples.height,
+            samples.channels(),
+            self.max_iterations,
+        );
+
+        samples.bytes = params.permute(&samples);
+        Ok(samples.into_image())
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        arnold::check_max_iterations(self.max_iterations)?;
+        let mut samples = Samples::new(image)?;
+        let params = Cat3DParams::from_seed(
+            seed,
+            "cat3d",
+            samples.width,
+            samples.height,
+            samples.channels(),
+            self.max_iterations,
+        );
+
+        samples.bytes = params.unpermute(&samples);
+        Ok(samples.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::testing::{image, image_with_alpha, seed};
+
+    #[test]
+    fn map_is_a_bijection() {
+        for channels in [1, 3, 4] {
+            let params = Cat3DParams::from_seed(
+                &seed(1),
+                "cat3d",
+                40,
+                30,
+                channels,
+                arnold::DEFAULT_MAX_ITERATIONS,
+            );
+            let mut hit = vec![false; 40 * 30 * channels];
+
+            for c in 0..channels as u64 {
+                for y in 0..30 {
+                    for x in 0..40 {
+                        let (nx, ny, nc) = params.forward(x, y, c);
+                        let index = ((ny * 40 + nx) * channels as u64 + nc) as usize;
+                        assert!(!std::mem::replace(&mut hit[index], true));
+                        assert_eq!(params.inverse(nx, ny, nc), (x, y, c));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_move_across_channels() {
+        // Every channel holds its own value, so a value in another channel has moved there.
+        let plaintext: DynamicImage = RgbaImage::from_pixel(40, 30, Rgba([0, 85, 170, 255])).into();
+        let ciphertext = CatMap3D::default()
+            .encrypt(plaintext.clone(), &seed(1))
+            .unwrap();
+
+        let levels = [0, 85, 170, 255];
+        for (channel, level) in levels.into_iter().enumerate() {
+            let moved = ciphertext
+                .as_bytes()
+                .iter()
+                .skip(channel)
+                .step_by(4)
+                .filter(|&&sample| sample != level)
+                .count();
+            assert!(moved > 0, "channel {channel}");
+        }
+
+        let count = |image: &DynamicImage, level| {
+            image
+                .as_bytes()
+                .iter()
+                .filter(|&&sample| sample == level)
+                .count()
+        };
+        for level in levels {
+            assert_eq!(count(&ciphertext, level), count(&plaintext, level));
+        }
+    }
+
+    #[test]
+    fn decrypt_restores_degenerate_and_rectangular_images() {
+        let cipher = CatMap3D::default();
+
+        for (width, height) in [(1, 1), (1, 17), (17, 1), (13, 9)] {
+            for plaintext in [image(width, height), image_with_alpha(width, height)] {
+                let ciphertext = cipher.encrypt(plaintext.clone(), &seed(2)).unwrap();
+                assert_eq!(ciphertext.dimensions(), (width, height));
+                assert_eq!(cipher.decrypt(ciphertext, &seed(2)).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_iterations_are_rejected() {
+        for max_iterations in [0, 65] {
+            let cipher = CatMap3D { max_iterations };
+
+            assert!(matches!(
+                cipher.encrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+            assert!(matches!(
+                cipher.decrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::onedim::{self, Map};
+use crate::registry::{self, ParamSpec};
+use crate::{CipherError, ImageCipher, Seed};
+
+/// Hyperchaotic Chen system permutation-diffusion cipher.
+///
+/// The continuous system is integrated with fixed-step RK4 from a key-derived initial state
+/// and `r`, its transient is discarded, and every step is quantized into one value in
+/// `[0, 1)`, which the [permutation-diffusion scheme](crate::onedim) of the one-dimensional
+/// maps takes as an orbit. The integration only adds
+/// and multiplies `f64`s in a fixed order, so the ciphertext is the same on every run and
+/// platform.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChenSystem {
+    /// RK4 steps dropped before each orbit is used.
+    pub transient: u32,
+}
+
+pub(crate) const DEFAULT_TRANSIENT: u32 = 3000;
+
+pub(crate) const PARAMS: &[ParamSpec] = &[registry::transient_param(DEFAULT_TRANSIENT)];
+
+impl Default for ChenSystem {
+    fn default() -> Self {
+        Self {
+            transient: DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+/// Parameters of the hyperchaotic Chen system
+/// `x' = a(y - x) + w, y' = dx - xz + cy, z' = xy - bz, w' = yz + rw`.
+const CHEN_A: f64 = 35.0;
+const CHEN_B: f64 = 3.0;
+const CHEN_C: f64 = 12.0;
+const CHEN_D: f64 = 7.0;
+/// RK4 step size.
+const STEP: f64 = 0.001;
+
+/// Key-seeded orbit of the hyperchaotic Chen system, integrated with fixed-step RK4.
+pub struct HyperChen {
+    state: [f64; 4],
+    r: f64,
+}
+
+impl HyperChen {
+    /// Draws the initial state and `r` from `unit`. `r` is kept inside `(0.085, 0.798)`,
+    /// where the system has two positive Lyapunov exponents.
+    fn draw(unit: impl Fn(&str) -> f64) -> Self {
+        Self {
+            state: [
+                -10.0 + 20.0 * unit("x0"),
+                -10.0 + 20.0 * unit("y0"),
+                20.0 * unit("z0"),
+                -10.0 + 20.0 * unit("w0"),
+            ],
+            r: 0.2 + 0.4 * unit("r"),
+        }
+    }
+
+    pub(crate) fn from_seed(seed: &Seed, transient: u32) -> Self {
+        let mut system = Self::draw(|name| seed.unit(&format!("hyperchaos.{name}")));
+
+        for _ in 0..transient {
+            system.integrate();
+        }
+        system
+    }
+
+    fn derivative(&self, [x, y, z, w]: [f64; 4]) -> [f64; 4] {
+        [
+            CHEN_A * (y - x) + w,
+            CHEN_D * x - x * z + CHEN_C * y,
+            x * y - CHEN_B * z,
+            y * z + self.r * w,
+        ]
+    }
+
+    fn integrate(&mut self) {
+        let offset = |state: [f64; 4], slope: [f64; 4], scale: f64| {
+            std::array::from_fn(|i| state[i] + scale * slope[i])
+        };
+
+        let k1 = self.derivative(self.state);
+        let k2 = self.derivative(offset(self.state, k1, STEP / 2.0));
+        let k3 = self.derivative(offset(self.state, k2, STEP / 2.0));
+        let k4 = self.derivative(offset(self.state, k3, STEP));
+
+        self.state = std::array::from_fn(|i| {
+            self.state[i] + STEP / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i])
+        });
+    }
+
+    /// Advances the orbit and quantizes each state variable into `[0, 1)`.
+    pub(crate) fn next(&mut self) -> [f64; 4] {
+        self.integrate();
+        self.state.map(|value| (value.abs() * 1e8).fract())
+    }
+}
+
+impl Map for HyperChen {
+    const NAME: &'static str = "chen";
+    const TRANSIENT: u32 = DEFAULT_TRANSIENT;
+
+    fn new(unit: impl Fn(&str) -> f64) -> Self {
+        Self::draw(unit)
+    }
+
+    /// One RK4 step, with the quantized state variables folded into a single value.
+    fn step(&mut self) -> f64 {
+        self.next().iter().sum::<f64>().fract()
+    }
+}
+
+impl Drop for HyperChen {
+    fn drop(&mut self) {
+        self.state.zeroize();
+        self.r.zeroize();
+    }
+}
+
+impl ImageCipher for ChenSystem {
+    fn name(&self) -> &'static str {
+        "chen-system"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::encrypt::<HyperChen>(image, seed, self.transient)
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::decrypt::<HyperChen>(image, seed, self.transient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::testing::{image, seed};
+
+    #[test]
+    fn ciphertext_is_the_same_on_every_run() {
+        let ciphertext = ChenSystem::default()
+            .encrypt(image(32, 32), &seed(1))
+            .unwrap();
+
+        // Recorded once; a different digest means the integration changed.
+        let digest = Sha256::digest(ciphertext.as_bytes());
+        assert_eq!(format!("{digest:x}")[..16], *"1bb2539f7181f7a9");
+    }
+}
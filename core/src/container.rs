@@ -0,0 +1,568 @@
+//! Self-describing PNG container for encrypted images.
+//!
+//! The ciphertext is stored as ordinary PNG pixel data, and a versioned JSON [`Header`] is
+//! kept in a private ancillary chunk right after `IHDR`. The header carries everything
+//! except the key that is needed to decrypt the file. Floating-point ciphertexts have no PNG
+//! representation, so each `f32` sample is stored as its bit pattern split over two
+//! 16-bit samples, which doubles the stored width.
+//!
+//! Both the header and the stored samples are authenticated with HMAC-SHA256 under a key
+//! derived from the password. The header also holds a key check value, so decrypting with
+//! the wrong key and decrypting a modified file fail with different errors.
+//!
+//! Every file gets a fresh KDF salt, so files encrypted with the same key never share a
+//! seed. A [`NonceMode`] additionally makes the parameters depend on a nonce kept in the
+//! header, which the key check and the MAC do not.
+//!
+//! The ciphertext keeps the channels of the source image, and an [`AlphaMode`] decides
+//! whether its alpha channel is encrypted, stored as it is or left out.
+
+use std::io::Cursor;
+
+use hmac::{Hmac, Mac};
+use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat, Pixel};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::kdf::NONCE_LEN;
+use crate::samples::buffer;
+use crate::{CipherError, ImageCipher, KdfParams, NonceMode, Seed, registry};
+
+/// Current container version. It changes with the header layout and with the output of
+/// any cipher, so older files are refused instead of decrypting to noise.
+pub const VERSION: u32 = 7;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Private (`m`), unsafe-to-copy (`D`) ancillary chunk that holds the header. Editors that
+/// do not know it drop it when they change the pixels, since it only matches the original.
+const HEADER_CHUNK: [u8; 4] = *b"imGD";
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// Length of the signature plus the `IHDR` chunk, which always comes first.
+const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+
+/// What happens to the alpha channel of a source image that has one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum AlphaMode {
+    /// Encrypted along with the color channels.
+    #[default]
+    Encrypt,
+    /// Stored unencrypted next to the encrypted color channels, so the shape of the image
+    /// stays visible but transparency survives without the key.
+    Keep,
+    /// Left out, so the ciphertext is smaller and the decrypted image has no alpha.
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+    /// Registry name of the cipher.
+    pub method: String,
+    /// Settings of the cipher, as returned by [`ImageCipher::params`].
+    pub params: serde_json::Value,
+    pub kdf: KdfParams,
+    pub nonce_mode: NonceMode,
+    /// Mixed into the seed the cipher runs under, see [`Seed::with_nonce`].
+    pub nonce: Option<[u8; NONCE_LEN]>,
+    pub alpha_mode: AlphaMode,
+    /// Dimensions of the source image.
+    pub width: u32,
+    pub height: u32,
+    /// Color type of the source image, restored after decryption. It has no alpha if
+    /// [`AlphaMode::Drop`] removed it.
+    #[serde(with = "color_type")]
+    pub color_type: ColorType,
+    /// Color type of the ciphertext, before it was packed into PNG samples.
+    #[serde(with = "color_type")]
+    pub cipher_color_type: ColorType,
+    /// Format of the source file, as its canonical extension.
+    pub source_format: Option<String>,
+    /// Identifies the key without revealing it.
+    pub key_check: [u8; 16],
+    /// HMAC-SHA256 over the header with a zeroed tag, followed by the stored samples.
+    pub tag: [u8; 32],
+}
+
+/// Encrypts `image` with `cipher` under a fresh KDF salt and returns the PNG file bytes.
+pub fn encrypt(
+    image: DynamicImage,
+    source_format: Option<ImageFormat>,
+    cipher: &dyn ImageCipher,
+    key: &str,
+    nonce_mode: NonceMode,
+    alpha_mode: AlphaMode,
+) -> Result<Vec<u8>, CipherError> {
+    let (image, alpha) = match alpha_mode {
+        AlphaMode::Encrypt => (image, None),
+        AlphaMode::Keep => split_alpha(image),
+        AlphaMode::Drop => (split_alpha(image).0, None),
+    };
+
+    let kdf = KdfParams::generate()?;
+    let seed = cipher.hash(key, &kdf)?;
+    let nonce = nonce_mode.nonce(&image, &seed)?;
+
+    let mut header = Header {
+        version: VERSION,
+        width: image.width(),
+        height: image.height(),
+        color_type: alpha.as_ref().unwrap_or(&image).color(),
+        cipher_color_type: image.color(),
+        source_format: source_format.map(|format| format.extensions_str()[0].to_string()),
+        method: cipher.name().to_string(),
+        params: cipher.params(),
+        kdf,
+        nonce_mode,
+        nonce,
+        alpha_mode,
+        key_check: key_check(&seed),
+        tag: [0; 32],
+    };
+
+    let image_seed = nonce.map(|nonce| seed.with_nonce(&nonce));
+    let ciphertext = cipher.encrypt(image, image_seed.as_ref().unwrap_or(&seed))?;
+    let ciphertext = match &alpha {
+        Some(alpha) => with_alpha(ciphertext, alpha),
+        None => ciphertext,
+    };
+    header.cipher_color_type = ciphertext.color();
+
+    let packed = pack(ciphertext)?;
+    header.tag = authenticate(&header, &packed, &seed)
+        .finalize()
+        .into_bytes()
+        .into();
+
+    let mut png = Vec::new();
+    packed.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    let json = serde_json::to_vec(&header).expect("header serializes to JSON");
+    png.splice(IHDR_END..IHDR_END, chunk(HEADER_CHUNK, &json));
+    Ok(png)
+}
+
+/// Decrypts a file written by [`encrypt`] using only `key`.
+///
+/// Fails with [`CipherError::WrongKey`] if `key` is not the one the file was encrypted
+/// with, and with [`CipherError::Tampered`] if the header or the pixels were changed.
+/// A changed salt or KDF cost is indistinguishable from a wrong key.
+pub fn decrypt(bytes: &[u8], key: &str) -> Result<(DynamicImage, Header), CipherError> {
+    let header = read_header(bytes)?;
+    let cipher = registry::lookup(&header.method)
+        .ok_or_else(|| CipherError::InvalidHeader(format!("unknown method {}", header.method)))?
+        .build(&header.params)?;
+
+    let seed = cipher.hash(key, &header.kdf)?;
+    if key_check(&seed) != header.key_check {
+        return Err(CipherError::WrongKey);
+    }
+
+    // The stored PNG always decodes, so a broken stream means the file was changed.
+    let packed = image::load_from_memory_with_format(bytes, ImageFormat::Png)
+        .map_err(|_| CipherError::Tampered)?;
+    authenticate(&header, &packed, &seed)
+        .verify_slice(&header.tag)
+        .map_err(|_| CipherError::Tampered)?;
+
+    let ciphertext = unpack(packed, header.cipher_color_type)?;
+    if ciphertext.width() != header.width || ciphertext.height() != header.height {
+        return Err(CipherError::InvalidDimensions {
+            width: ciphertext.width(),
+            height: ciphertext.height(),
+        });
+    }
+
+    let (ciphertext, alpha) = match header.alpha_mode {
+        AlphaMode::Keep => split_alpha(ciphertext),
+        AlphaMode::Encrypt | AlphaMode::Drop => (ciphertext, None),
+    };
+
+    let image_seed = header.nonce.map(|nonce| seed.with_nonce(&nonce));
+    let image = cipher.decrypt(ciphertext, image_seed.as_ref().unwrap_or(&seed))?;
+    let image = match &alpha {
+        Some(alpha) => with_alpha(image, alpha),
+        None => image,
+    };
+
+    Ok((convert(image, header.color_type), header))
+}
+
+/// Reads the header without decrypting anything. KDF costs beyond the limits of
+/// [`KdfParams::check`] are rejected, since nothing vouches for them yet.
+pub fn read_header(bytes: &[u8]) -> Result<Header, CipherError> {
+    let json = find_chunk(bytes, HEADER_CHUNK)?.ok_or(CipherError::NotEncrypted)?;
+    let invalid = |err: serde_json::Error| CipherError::InvalidHeader(err.to_string());
+    let value: serde_json::Value = serde_json::from_slice(json).map_err(invalid)?;
+
+    match value.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version == u64::from(VERSION) => {
+            let header: Header = serde_json::from_value(value).map_err(invalid)?;
+            header.kdf.check()?;
+            Ok(header)
+        }
+        Some(version) => Err(CipherError::UnsupportedVersion(version as u32)),
+        None => Err(CipherError::InvalidHeader("missing version".to_string())),
+    }
+}
+
+/// Reads the stored ciphertext without the key, for analysis. Nothing is authenticated.
+pub fn ciphertext(bytes: &[u8]) -> Result<(DynamicImage, Header), CipherError> {
+    let header = read_header(bytes)?;
+    let packed = image::load_from_memory_with_format(bytes, ImageFormat::Png)?;
+
+    Ok((unpack(packed, header.cipher_color_type)?, header))
+}
+
+fn key_check(seed: &Seed) -> [u8; 16] {
+    seed.subkey("imaged.key-check")[..16]
+        .try_into()
+        .expect("subkey is 32 bytes")
+}
+
+/// MAC over the header with a zeroed tag and the stored samples, with 16-bit samples
+/// taken big-endian so the tag does not depend on the platform.
+fn authenticate(header: &Header, packed: &DynamicImage, seed: &Seed) -> HmacSha256 {
+    let mut unsigned = header.clone();
+    unsigned.tag = [0; 32];
+
+    let mut mac = HmacSha256::new_from_slice(seed.subkey("imaged.mac").as_slice())
+        .expect("HMAC accepts any key length");
+    mac.update(&serde_json::to_vec(&unsigned).expect("header serializes to JSON"));
+
+    match packed.as_flat_samples_u16() {
+        Some(samples) => {
+            let bytes: Vec<u8> = samples
+                .samples
+                .iter()
+                .flat_map(|sample| sample.to_be_bytes())
+                .collect();
+            mac.update(&bytes);
+        }
+        None => mac.update(packed.as_bytes()),
+    }
+
+    mac
+}
+
+fn chunk(kind: [u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&kind);
+    hasher.update(data);
+
+    let mut chunk = Vec::with_capacity(data.len() + 12);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(&kind);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(&hasher.finalize().to_be_bytes());
+    chunk
+}
+
+fn find_chunk(png: &[u8], kind: [u8; 4]) -> Result<Option<&[u8]>, CipherError> {
+    let mut rest = png
+        .strip_prefix(&PNG_SIGNATURE)
+        .ok_or(CipherError::NotEncrypted)?;
+
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes(rest[..4].try_into().expect("4 bytes")) as usize;
+        if rest.len() < len + 12 {
+            break;
+        }
+
+        if rest[4..8] == kind {
+            let crc = u32::from_be_bytes(rest[len + 8..len + 12].try_into().expect("4 bytes"));
+            if crc32fast::hash(&rest[4..len + 8]) != crc {
+                return Err(CipherError::InvalidHeader("checksum mismatch".to_string()));
+            }
+            return Ok(Some(&rest[8..len + 8]));
+        }
+
+        rest = &rest[len + 12..];
+    }
+
+    Ok(None)
+}
+
+/// Turns the ciphertext into something the PNG encoder accepts.
+fn pack(image: DynamicImage) -> Result<DynamicImage, CipherError> {
+    let split = |samples: &[f32]| -> Vec<u16> {
+        samples
+            .iter()
+            .flat_map(|sample| {
+                let bits = sample.to_bits();
+                [(bits >> 16) as u16, bits as u16]
+            })
+            .collect()
+    };
+    let (width, height) = (image.width() * 2, image.height());
+
+    match image {
+        DynamicImage::ImageRgb32F(buffer) => Ok(DynamicImage::ImageRgb16(
+            ImageBuffer::from_raw(width, height, split(&buffer)).expect("sample count matches"),
+        )),
+        DynamicImage::ImageRgba32F(buffer) => Ok(DynamicImage::ImageRgba16(
+            ImageBuffer::from_raw(width, height, split(&buffer)).expect("sample count matches"),
+        )),
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_)
+        | DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => Ok(image),
+        _ => Err(CipherError::UnsupportedColorType(image.color())),
+    }
+}
+
+/// Reverses [`pack`] given the color type recorded in the header.
+fn unpack(packed: DynamicImage, color_type: ColorType) -> Result<DynamicImage, CipherError> {
+    let join = |samples: &[u16]| -> Vec<f32> {
+        samples
+            .chunks_exact(2)
+            .map(|pair| f32::from_bits((u32::from(pair[0]) << 16) | u32::from(pair[1])))
+            .collect()
+    };
+    let (width, height) = (packed.width() / 2, packed.height());
+
+    match (packed, color_type) {
+        (DynamicImage::ImageRgb16(buffer), ColorType::Rgb32F) => Ok(DynamicImage::ImageRgb32F(
+            ImageBuffer::from_raw(width, height, join(&buffer))
+                .ok_or(CipherError::InvalidDimensions { width, height })?,
+        )),
+        (DynamicImage::ImageRgba16(buffer), ColorType::Rgba32F) => Ok(DynamicImage::ImageRgba32F(
+            ImageBuffer::from_raw(width, height, join(&buffer))
+                .ok_or(CipherError::InvalidDimensions { width, height })?,
+        )),
+        (packed, color_type) if packed.color() == color_type => Ok(packed),
+        (_, color_type) => Err(CipherError::UnsupportedColorType(color_type)),
+    }
+}
+
+/// Splits off the alpha channel of `image`. The second half is `image` itself, to take the
+/// alpha from with [`with_alpha`], and `None` if there is no alpha.
+fn split_alpha(image: DynamicImage) -> (DynamicImage, Option<DynamicImage>) {
+    let color = match &image {
+        DynamicImage::ImageLumaA8(_) => image.to_luma8().into(),
+        DynamicImage::ImageLumaA16(_) => image.to_luma16().into(),
+        DynamicImage::ImageRgba8(_) => image.to_rgb8().into(),
+        DynamicImage::ImageRgba16(_) => image.to_rgb16().into(),
+        DynamicImage::ImageRgba32F(_) => image.to_rgb32f().into(),
+        _ => return (image, None),
+    };
+    (color, Some(image))
+}
+
+/// Adds the alpha channel of `alpha` to `image`, at the depth of `image`. Conversions
+/// between depths round-trip, so alpha that was widened for a floating-point ciphertext
+/// comes back exactly.
+fn with_alpha(image: DynamicImage, alpha: &DynamicImage) -> DynamicImage {
+    fn interleave<P: Pixel>(color: &[P::Subpixel], alpha: &[P]) -> Vec<P::Subpixel> {
+        let channels = color.len() / alpha.len();
+        color
+            .chunks_exact(channels)
+            .zip(alpha)
+            .flat_map(|(pixel, alpha)| {
+                pixel
+                    .iter()
+                    .copied()
+                    .chain([alpha.channels()[P::CHANNEL_COUNT as usize - 1]])
+            })
+            .collect()
+    }
+
+    let (width, height) = (image.width(), image.height());
+    let alpha8 = || alpha.to_luma_alpha8().pixels().copied().collect::<Vec<_>>();
+    let alpha16 = || {
+        alpha
+            .to_luma_alpha16()
+            .pixels()
+            .copied()
+            .collect::<Vec<_>>()
+    };
+
+    match image {
+        DynamicImage::ImageLuma8(color) => {
+            DynamicImage::ImageLumaA8(buffer(width, height, interleave(&color, &alpha8())))
+        }
+        DynamicImage::ImageRgb8(color) => {
+            DynamicImage::ImageRgba8(buffer(width, height, interleave(&color, &alpha8())))
+        }
+        DynamicImage::ImageLuma16(color) => {
+            DynamicImage::ImageLumaA16(buffer(width, height, interleave(&color, &alpha16())))
+        }
+        DynamicImage::ImageRgb16(color) => {
+            DynamicImage::ImageRgba16(buffer(width, height, interleave(&color, &alpha16())))
+        }
+        DynamicImage::ImageRgb32F(color) => {
+            let alpha: Vec<_> = alpha.to_rgba32f().pixels().copied().collect();
+            DynamicImage::ImageRgba32F(buffer(width, height, interleave(&color, &alpha)))
+        }
+        image => image,
+    }
+}
+
+/// Converts the decrypted image back to the color type of the source.
+fn convert(image: DynamicImage, color_type: ColorType) -> DynamicImage {
+    match color_type {
+        ColorType::L8 => image.to_luma8().into(),
+        ColorType::La8 => image.to_luma_alpha8().into(),
+        ColorType::Rgb8 => image.to_rgb8().into(),
+        ColorType::Rgba8 => image.to_rgba8().into(),
+        ColorType::L16 => image.to_luma16().into(),
+        ColorType::La16 => image.to_luma_alpha16().into(),
+        ColorType::Rgb16 => image.to_rgb16().into(),
+        ColorType::Rgba16 => image.to_rgba16().into(),
+        ColorType::Rgb32F => image.to_rgb32f().into(),
+        ColorType::Rgba32F => image.to_rgba32f().into(),
+        _ => image,
+    }
+}
+
+/// Serializes [`ColorType`], which has no serde support of its own, by variant name.
+mod color_type {
+    use image::ColorType;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    const NAMES: [(ColorType, &str); 10] = [
+        (ColorType::L8, "L8"),
+        (ColorType::La8, "La8"),
+        (ColorType::Rgb8, "Rgb8"),
+        (ColorType::Rgba8, "Rgba8"),
+        (ColorType::L16, "L16"),
+        (ColorType::La16, "La16"),
+        (ColorType::Rgb16, "Rgb16"),
+        (ColorType::Rgba16, "Rgba16"),
+        (ColorType::Rgb32F, "Rgb32F"),
+        (ColorType::Rgba32F, "Rgba32F"),
+    ];
+
+    pub fn serialize<S: Serializer>(
+        color_type: &ColorType,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let (_, name) = NAMES
+            .iter()
+            .find(|(known, _)| known == color_type)
+            .ok_or_else(|| serde::ser::Error::custom("unsupported color type"))?;
+        serializer.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ColorType, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        NAMES
+            .iter()
+            .find(|(_, known)| *known == name)
+            .map(|(color_type, _)| *color_type)
+            .ok_or_else(|| D::Error::custom(format!("unknown color type {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HyperChaosSVD;
+    use crate::testing::{image, image_with_alpha};
+
+    /// Rewrites the header of `png` with `edit`, keeping the chunk checksum valid.
+    fn edit_header(png: &[u8], edit: impl FnOnce(&mut serde_json::Value)) -> Vec<u8> {
+        let json = find_chunk(png, HEADER_CHUNK).unwrap().unwrap();
+        let mut header: serde_json::Value = serde_json::from_slice(json).unwrap();
+        edit(&mut header);
+
+        let mut edited = png.to_vec();
+        edited.splice(
+            IHDR_END..IHDR_END + json.len() + 12,
+            chunk(HEADER_CHUNK, &serde_json::to_vec(&header).unwrap()),
+        );
+        edited
+    }
+
+    fn encrypt_default(image: DynamicImage) -> Vec<u8> {
+        let cipher = registry::lookup("arnold-cat").unwrap().default_cipher();
+        encrypt(
+            image,
+            None,
+            cipher.as_ref(),
+            "key",
+            NonceMode::Key,
+            AlphaMode::Encrypt,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn inflated_kdf_costs_are_rejected_before_deriving() {
+        let png = encrypt_default(image(8, 8));
+        assert!(decrypt(&edit_header(&png, |_| {}), "key").is_ok());
+
+        for (name, cost) in [
+            ("memory_kib", 4_000_000_000u32),
+            ("iterations", u32::MAX),
+            ("parallelism", 1 << 20),
+        ] {
+            let forged = edit_header(&png, |header| header["kdf"][name] = cost.into());
+            assert!(matches!(
+                decrypt(&forged, "key"),
+                Err(CipherError::InvalidHeader(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn every_alpha_mode_round_trips_8_bit_color_types() {
+        let sources: [DynamicImage; 4] = [
+            image(13, 9).into_luma8().into(),
+            image_with_alpha(13, 9).into_luma_alpha8().into(),
+            image(13, 9),
+            image_with_alpha(13, 9),
+        ];
+
+        for method in ["arnold-cat", "hyper-chaos-svd"] {
+            let cipher = registry::lookup(method).unwrap().default_cipher();
+            let tolerance = if method == "hyper-chaos-svd" {
+                HyperChaosSVD::TOLERANCE
+            } else {
+                0
+            };
+
+            for source in &sources {
+                for alpha_mode in [AlphaMode::Encrypt, AlphaMode::Keep, AlphaMode::Drop] {
+                    let png = encrypt(
+                        source.clone(),
+                        None,
+                        cipher.as_ref(),
+                        "key",
+                        NonceMode::Key,
+                        alpha_mode,
+                    )
+                    .unwrap();
+                    let (decrypted, header) = decrypt(&png, "key").unwrap();
+                    if method == "hyper-chaos-svd" && !source.color().has_color() {
+                        // A grayscale ciphertext is a single plane of packed floats.
+                        assert!(matches!(
+                            header.cipher_color_type,
+                            ColorType::Rgb16 | ColorType::Rgba16
+                        ));
+                    }
+
+                    let expected = match alpha_mode {
+                        AlphaMode::Drop => split_alpha(source.clone()).0,
+                        _ => source.clone(),
+                    };
+                    let context = format!("{method} {:?} {alpha_mode:?}", source.color());
+                    assert_eq!(decrypted.color(), expected.color(), "{context}");
+                    let error = decrypted
+                        .as_bytes()
+                        .iter()
+                        .zip(expected.as_bytes())
+                        .map(|(a, b)| a.abs_diff(*b))
+                        .max();
+                    assert!(error <= Some(tolerance), "{context}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use image::{ColorType, ImageError};
+
+#[derive(Debug)]
+pub enum CipherError {
+    /// The cipher cannot work on images of this color type.
+    UnsupportedColorType(ColorType),
+    /// The image is empty or does not have the dimensions it should.
+    InvalidDimensions {
+        width: u32,
+        height: u32,
+    },
+    /// A cipher or KDF parameter is out of range.
+    InvalidParameter(String),
+    /// The file is not an imaged container.
+    NotEncrypted,
+    /// The container header is malformed.
+    InvalidHeader(String),
+    /// The container was written by a different container version of imaged.
+    UnsupportedVersion(u32),
+    /// Authentication failed because the key is not the one the image was encrypted with.
+    WrongKey,
+    /// Authentication failed because the file was changed after encryption.
+    Tampered,
+    Io(io::Error),
+    Image(ImageError),
+}
+
+impl Display for CipherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherError::UnsupportedColorType(color_type) => {
+                write!(f, "Unsupported color type {color_type:?}")
+            }
+            CipherError::InvalidDimensions { width, height } => {
+                write!(f, "Invalid image dimensions {width}x{height}")
+            }
+            CipherError::InvalidParameter(message) => write!(f, "Invalid parameter: {message}"),
+            CipherError::NotEncrypted => write!(f, "Not an image encrypted by imaged"),
+            CipherError::InvalidHeader(message) => write!(f, "Invalid imaged header: {message}"),
+            CipherError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported header version {version}")
+            }
+            CipherError::WrongKey => write!(f, "Wrong key"),
+            CipherError::Tampered => write!(f, "The file was modified after encryption"),
+            CipherError::Io(err) => write!(f, "{err}"),
+            CipherError::Image(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for CipherError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CipherError::Io(err) => Some(err),
+            CipherError::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CipherError {
+    fn from(err: io::Error) -> Self {
+        CipherError::Io(err)
+    }
+}
+
+impl From<ImageError> for CipherError {
+    fn from(err: ImageError) -> Self {
+        CipherError::Image(err)
+    }
+}
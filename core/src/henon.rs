@@ -0,0 +1,203 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::onedim::{self, DEFAULT_TRANSIENT};
+use crate::rng::{ChaoticRng, CycleCheck, Orbit};
+use crate::samples::Samples;
+use crate::{CipherError, ImageCipher, Seed, registry};
+
+/// Hénon map stream cipher.
+///
+/// The orbit of `x' = 1 - a*x^2 + y`, `y' = b*x` is seeded from the key, its transient is
+/// discarded and every following `x` is quantized into 32 keystream bits. Each sample is
+/// then diffused with ciphertext feedback, `c[i] = ((p[i] + c[i - 1]) mod 256) ^ k[i]`, so a
+/// change to one sample carries over to every sample after it.
+///
+/// Both the orbit and the encryption feedback are sequential. Decryption only needs the
+/// previous ciphertext sample, so it runs in parallel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HenonMap {
+    /// Iterations dropped before the orbit settles on the attractor.
+    pub transient: u32,
+}
+
+impl Default for HenonMap {
+    fn default() -> Self {
+        Self {
+            transient: DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+/// Key-seeded orbit of the Hénon map.
+///
+/// `a` and `b` stay next to the classic `(1.4, 0.3)` and the orbit starts close to the
+/// origin, inside the basin of the chaotic attractor. That range still contains periodic
+/// windows, so whenever the orbit diverges or returns to the last checkpoint it is
+/// reseeded with the next set of key-derived parameters.
+pub struct HenonOrbit {
+    seed: Seed,
+    label: String,
+    transient: u32,
+    attempt: u32,
+    a: f64,
+    b: f64,
+    x: f64,
+    y: f64,
+    cycle: CycleCheck,
+}
+
+impl HenonOrbit {
+    fn reseed(&mut self) {
+        let unit = |name: &str| {
+            self.seed
+                .unit(&format!("{}.{name}.{}", self.label, self.attempt))
+        };
+
+        self.x = -0.1 + 0.2 * unit("x0");
+        self.y = -0.1 + 0.2 * unit("y0");
+        self.a = 1.38 + 0.02 * unit("a");
+        self.b = 0.28 + 0.02 * unit("b");
+        self.attempt += 1;
+
+        for _ in 0..self.transient {
+            self.step();
+        }
+        self.cycle = CycleCheck::new(self.x);
+    }
+
+    fn step(&mut self) {
+        (self.x, self.y) = (1.0 - self.a * self.x * self.x + self.y, self.b * self.x);
+    }
+}
+
+impl Orbit for HenonOrbit {
+    const TRANSIENT: u32 = DEFAULT_TRANSIENT;
+
+    fn new(seed: &Seed, label: &str, transient: u32) -> Self {
+        let mut orbit = Self {
+            seed: seed.clone(),
+            label: label.to_string(),
+            transient,
+            attempt: 0,
+            a: 0.0,
+            b: 0.0,
+            x: 0.0,
+            y: 0.0,
+            cycle: CycleCheck::new(f64::NAN),
+        };
+        orbit.reseed();
+        orbit
+    }
+
+    /// Low 32 bits of `|x|` scaled by `10^14`, where the digits are well mixed.
+    fn next_u32(&mut self) -> u32 {
+        loop {
+            self.step();
+
+            if !self.x.is_finite() || self.cycle.is_cycling(self.x) {
+                self.reseed();
+                continue;
+            }
+            return (self.x.abs() * 1e14) as u64 as u32;
+        }
+    }
+}
+
+impl Drop for HenonOrbit {
+    fn drop(&mut self) {
+        self.a.zeroize();
+        self.b.zeroize();
+        self.x.zeroize();
+        self.y.zeroize();
+    }
+}
+
+/// Generator of the keystream.
+fn keystream(seed: &Seed, transient: u32) -> ChaoticRng<HenonOrbit> {
+    ChaoticRng::new(seed, "henon", transient)
+}
+
+impl ImageCipher for HenonMap {
+    fn name(&self) -> &'static str {
+        "henon-map"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        let mut samples = Samples::new(image)?;
+        onedim::diffuse(
+            &mut samples.bytes,
+            &mut keystream(seed, self.transient),
+            onedim::iv(seed, "henon"),
+        );
+        Ok(samples.into_image())
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        let mut samples = Samples::new(image)?;
+        samples.bytes = onedim::undiffuse(
+            &samples.bytes,
+            &mut keystream(seed, self.transient),
+            onedim::iv(seed, "henon"),
+        );
+        Ok(samples.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::RngCore;
+
+    use super::*;
+    use crate::rng::CYCLE_WINDOW;
+    use crate::testing::{image, seed};
+
+    #[test]
+    fn decrypt_restores_the_image() {
+        let plaintext = image(40, 30);
+        let cipher = HenonMap::default();
+
+        let ciphertext = cipher.encrypt(plaintext.clone(), &seed(1)).unwrap();
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(cipher.decrypt(ciphertext, &seed(1)).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn keystream_depends_only_on_the_seed() {
+        let bytes = |byte| {
+            let mut bytes = [0; 4096];
+            keystream(&seed(byte), DEFAULT_TRANSIENT).fill_bytes(&mut bytes);
+            bytes
+        };
+
+        assert_eq!(bytes(1), bytes(1));
+        assert_ne!(bytes(1), bytes(2));
+    }
+
+    #[test]
+    fn diverging_orbit_is_reseeded() {
+        let mut orbit = HenonOrbit::new(&seed(1), "henon", DEFAULT_TRANSIENT);
+        orbit.x = 1e200;
+
+        orbit.next_u32();
+        assert_eq!(orbit.attempt, 2);
+        assert!(orbit.x.is_finite());
+    }
+
+    #[test]
+    fn periodic_orbit_is_reseeded() {
+        let mut orbit = HenonOrbit::new(&seed(1), "henon", DEFAULT_TRANSIENT);
+        // With a this small the map has an attracting fixed point.
+        orbit.a = 0.2;
+
+        let values: Vec<u32> = (0..2 * CYCLE_WINDOW).map(|_| orbit.next_u32()).collect();
+        assert_eq!(orbit.attempt, 2);
+        assert_ne!(values[values.len() - 2], values[values.len() - 1]);
+    }
+}
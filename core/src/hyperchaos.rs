@@ -0,0 +1,378 @@
+use image::DynamicImage;
+use nalgebra::{DMatrix, DVector};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::chen::{DEFAULT_TRANSIENT, HyperChen};
+use crate::samples::{Depth, buffer};
+use crate::{CipherError, ImageCipher, Seed, check_dimensions, registry};
+
+/// SVD based cipher driven by the hyperchaotic Chen system.
+///
+/// Every color channel is first masked with a chaotic matrix `N` and factored as
+/// `A + N = U * S * V^T`. A non-increasing, strictly positive chaotic offset is added to the
+/// singular values, and the rows of `U` and `V` are shuffled with chaotic permutations
+/// before the channel is reconstructed. The mask also keeps flat regions from reaching the
+/// SVD as exactly degenerate matrices, which it does not always factor correctly. The result is
+/// not representable with 8-bit samples, so the ciphertext is an `Rgb32F` image, or
+/// `Rgba32F` if the source has alpha, holding the reconstructed channels divided by 255
+/// (save it in a floating-point format such as OpenEXR). Alpha is carried over unchanged.
+/// There is no single-channel floating-point image, so a grayscale image is scrambled as
+/// one plane whose `f32` samples are split into high and low halves, like the container
+/// packs floats: the ciphertext is `Rgb16` with an unused third channel, or `Rgba16` with
+/// the alpha of the source. Decryption takes the number of planes from the color type of
+/// the ciphertext and returns the color type of the source. The channels are scrambled in
+/// parallel.
+///
+/// Because the offset keeps the singular values in the same order, decryption recovers the
+/// factorization from an SVD of the unshuffled ciphertext, subtracts the offset and then
+/// the mask.
+/// The only loss comes from the `f32` ciphertext samples: every decrypted sample is within
+/// [`HyperChaosSVD::TOLERANCE`] of the original. For that reason only 8-bit images are
+/// accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HyperChaosSVD {
+    /// RK4 steps dropped before the orbit settles on the attractor.
+    pub transient: u32,
+}
+
+impl Default for HyperChaosSVD {
+    fn default() -> Self {
+        Self {
+            transient: DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+impl HyperChaosSVD {
+    /// Largest difference between an original and a decrypted 8-bit sample.
+    pub const TOLERANCE: u8 = 1;
+}
+
+/// Upper bound of a single singular value offset.
+const OFFSET_SCALE: f64 = 255.0;
+/// Upper bound of a mask sample.
+const MASK_SCALE: f64 = 255.0;
+
+/// Chaotic material for scrambling one channel.
+struct ChannelKey {
+    mask: DMatrix<f64>,
+    row_permutation: Vec<usize>,
+    column_permutation: Vec<usize>,
+    /// Non-increasing offsets added to the singular values.
+    offsets: Vec<f64>,
+}
+
+impl Drop for ChannelKey {
+    fn drop(&mut self) {
+        self.mask.as_mut_slice().zeroize();
+        self.row_permutation.zeroize();
+        self.column_permutation.zeroize();
+        self.offsets.zeroize();
+    }
+}
+
+/// Draws the mask, row and column permutations and singular value offsets for every color
+/// channel of a `width x height` image from one hyperchaotic orbit.
+fn chaotic_sequence_generation(
+    seed: &Seed,
+    transient: u32,
+    width: usize,
+    height: usize,
+) -> [ChannelKey; 3] {
+    let mut system = HyperChen::from_seed(seed, transient);
+    let rank = width.min(height);
+
+    std::array::from_fn(|_| {
+        let mask = DMatrix::from_fn(height, width, |_, _| {
+            let [x, y, z, w] = system.next();
+            MASK_SCALE * (x + y + z + w).fract()
+        });
+        let rows: Vec<f64> = (0..height).map(|_| system.next()[0]).collect();
+        let columns: Vec<f64> = (0..width).map(|_| system.next()[1]).collect();
+        let steps: Vec<f64> = (0..rank)
+            .map(|_| {
+                let [_, _, z, w] = system.next();
+                1.0 + (OFFSET_SCALE - 1.0) * (z + w) / 2.0
+            })
+            .collect();
+
+        let mut offsets = steps;
+        for i in (0..rank.saturating_sub(1)).rev() {
+            offsets[i] += offsets[i + 1];
+        }
+
+        ChannelKey {
+            mask,
+            row_permutation: permutation(&rows),
+            column_permutation: permutation(&columns),
+            offsets,
+        }
+    })
+}
+
+/// Indices that sort `values` in ascending order.
+fn permutation(values: &[f64]) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    indices.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    indices
+}
+
+fn scramble(channel: DMatrix<f64>, key: &ChannelKey) -> DMatrix<f64> {
+    let svd = (channel + &key.mask).svd(true, true);
+    let u = svd.u.expect("U is computed");
+    let v_t = svd.v_t.expect("V^T is computed");
+    let singular_values = svd.singular_values.zip_map(
+        &DVector::from_column_slice(&key.offsets),
+        |value, offset| value + offset,
+    );
+
+    let u = DMatrix::from_fn(u.nrows(), u.ncols(), |i, j| u[(key.row_permutation[i], j)]);
+    let v_t = DMatrix::from_fn(v_t.nrows(), v_t.ncols(), |i, j| {
+        v_t[(i, key.column_permutation[j])]
+    });
+
+    u * DMatrix::from_diagonal(&singular_values) * v_t
+}
+
+fn unscramble(channel: DMatrix<f64>, key: &ChannelKey) -> DMatrix<f64> {
+    let mut unshuffled = DMatrix::zeros(channel.nrows(), channel.ncols());
+    for i in 0..channel.nrows() {
+        for j in 0..channel.ncols() {
+            unshuffled[(key.row_permutation[i], key.column_permutation[j])] = channel[(i, j)];
+        }
+    }
+
+    let svd = unshuffled.svd(true, true);
+    let u = svd.u.expect("U is computed");
+    let v_t = svd.v_t.expect("V^T is computed");
+    let singular_values = svd.singular_values.zip_map(
+        &DVector::from_column_slice(&key.offsets),
+        |value, offset| value - offset,
+    );
+
+    u * DMatrix::from_diagonal(&singular_values) * v_t - &key.mask
+}
+
+impl ImageCipher for HyperChaosSVD {
+    fn name(&self) -> &'static str {
+        "hyper-chaos-svd"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        check_dimensions(&image)?;
+        // Deeper samples would not survive the f32 ciphertext, so they are refused rather
+        // than truncated.
+        if Depth::of(image.color())? != Depth::U8 {
+            return Err(CipherError::UnsupportedColorType(image.color()));
+        }
+        let alpha = image.color().has_alpha();
+        let planes = if image.color().has_color() { 3 } else { 1 };
+        let image: DynamicImage = match (planes, alpha) {
+            (1, false) => image.into_luma8().into(),
+            (1, true) => image.into_luma_alpha8().into(),
+            (_, false) => image.into_rgb8().into(),
+            (_, true) => image.into_rgba8().into(),
+        };
+        let (width, height) = (image.width(), image.height());
+        let (columns, rows) = (width as usize, height as usize);
+        let stride = usize::from(image.color().channel_count());
+        let samples = image.as_bytes();
+
+        let keys = chaotic_sequence_generation(seed, self.transient, columns, rows);
+        let channels: Vec<DMatrix<f64>> = keys[..planes]
+            .par_iter()
+            .enumerate()
+            .map(|(channel, key)| {
+                let plane = DMatrix::from_fn(rows, columns, |row, column| {
+                    f64::from(samples[(row * columns + column) * stride + channel])
+                });
+                scramble(plane, key)
+            })
+            .collect();
+        let value = |channel: &DMatrix<f64>, index: usize| {
+            (channel[(index / columns, index % columns)] / 255.0) as f32
+        };
+
+        if planes == 1 {
+            let mut ciphertext =
+                Vec::with_capacity(samples.len() / stride * (3 + usize::from(alpha)));
+            for (index, pixel) in samples.chunks_exact(stride).enumerate() {
+                let bits = value(&channels[0], index).to_bits();
+                ciphertext.extend([(bits >> 16) as u16, bits as u16, 0]);
+                if alpha {
+                    ciphertext.push(u16::from(pixel[1]) * 257);
+                }
+            }
+
+            return Ok(if alpha {
+                DynamicImage::ImageRgba16(buffer(width, height, ciphertext))
+            } else {
+                DynamicImage::ImageRgb16(buffer(width, height, ciphertext))
+            });
+        }
+
+        let mut ciphertext = Vec::with_capacity(samples.len());
+        for (index, pixel) in samples.chunks_exact(stride).enumerate() {
+            ciphertext.extend(channels.iter().map(|channel| value(channel, index)));
+            if alpha {
+                ciphertext.push(f32::from(pixel[3]) / 255.0);
+            }
+        }
+
+        Ok(if alpha {
+            DynamicImage::ImageRgba32F(buffer(width, height, ciphertext))
+        } else {
+            DynamicImage::ImageRgb32F(buffer(width, height, ciphertext))
+        })
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        check_dimensions(&image)?;
+        let to_sample = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+
+        // The color type of the ciphertext, which the container records, gives the number
+        // of planes. Anything else cannot hold a ciphertext of this method.
+        let (planes, values, alpha): (usize, Vec<f64>, Option<Vec<u8>>) = match &image {
+            DynamicImage::ImageRgb32F(buffer) => (3, color_values(buffer.as_raw(), 3), None),
+            DynamicImage::ImageRgba32F(buffer) => (
+                3,
+                color_values(buffer.as_raw(), 4),
+                Some(
+                    buffer
+                        .pixels()
+                        .map(|pixel| to_sample(f64::from(pixel[3]) * 255.0))
+                        .collect(),
+                ),
+            ),
+            DynamicImage::ImageRgb16(buffer) => (1, gray_values(buffer.as_raw(), 3), None),
+            DynamicImage::ImageRgba16(buffer) => (
+                1,
+                gray_values(buffer.as_raw(), 4),
+                Some(
+                    buffer
+                        .pixels()
+                        .map(|pixel| to_sample(f64::from(pixel[3]) / 257.0))
+                        .collect(),
+                ),
+            ),
+            _ => return Err(CipherError::UnsupportedColorType(image.color())),
+        };
+        let (width, height) = (image.width(), image.height());
+        let (columns, rows) = (width as usize, height as usize);
+
+        let keys = chaotic_sequence_generation(seed, self.transient, columns, rows);
+        let channels: Vec<DMatrix<f64>> = keys[..planes]
+            .par_iter()
+            .enumerate()
+            .map(|(channel, key)| {
+                let plane = DMatrix::from_fn(rows, columns, |row, column| {
+                    values[(row * columns + column) * planes + channel] * 255.0
+                });
+                unscramble(plane, key)
+            })
+            .collect();
+
+        let pixels = rows * columns;
+        let mut plaintext = Vec::with_capacity(pixels * (planes + usize::from(alpha.is_some())));
+        for index in 0..pixels {
+            let (row, column) = (index / columns, index % columns);
+            plaintext.extend(
+                channels
+                    .iter()
+                    .map(|channel| to_sample(channel[(row, column)])),
+            );
+            if let Some(alpha) = &alpha {
+                plaintext.push(alpha[index]);
+            }
+        }
+
+        Ok(match (planes, alpha.is_some()) {
+            (1, false) => DynamicImage::ImageLuma8(buffer(width, height, plaintext)),
+            (1, true) => DynamicImage::ImageLumaA8(buffer(width, height, plaintext)),
+            (_, false) => DynamicImage::ImageRgb8(buffer(width, height, plaintext)),
+            (_, true) => DynamicImage::ImageRgba8(buffer(width, height, plaintext)),
+        })
+    }
+}
+
+/// The color samples of a floating-point ciphertext with `stride` channels per pixel.
+fn color_values(samples: &[f32], stride: usize) -> Vec<f64> {
+    samples
+        .chunks_exact(stride)
+        .flat_map(|pixel| pixel[..3].iter().map(|&sample| f64::from(sample)))
+        .collect()
+}
+
+/// The plane of a grayscale ciphertext, whose `f32` samples are split into the first two
+/// 16-bit channels of every pixel.
+fn gray_values(samples: &[u16], stride: usize) -> Vec<f64> {
+    samples
+        .chunks_exact(stride)
+        .map(|pixel| {
+            f64::from(f32::from_bits(
+                (u32::from(pixel[0]) << 16) | u32::from(pixel[1]),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ColorType;
+
+    use super::*;
+    use crate::testing::{image, image_with_alpha, seed};
+
+    #[test]
+    fn decrypt_is_within_the_tolerance() {
+        let cipher = HyperChaosSVD::default();
+        let gray = image(37, 23).into_luma8();
+
+        for (plaintext, cipher_color_type) in [
+            (image(37, 23), ColorType::Rgb32F),
+            (image_with_alpha(23, 37), ColorType::Rgba32F),
+            (gray.clone().into(), ColorType::Rgb16),
+            (
+                image_with_alpha(23, 37).into_luma_alpha8().into(),
+                ColorType::Rgba16,
+            ),
+            // Gray pixels in an RGB image are still scrambled and returned as RGB.
+            (
+                DynamicImage::from(gray).into_rgb8().into(),
+                ColorType::Rgb32F,
+            ),
+        ] {
+            let ciphertext = cipher.encrypt(plaintext.clone(), &seed(1)).unwrap();
+            assert_eq!(ciphertext.color(), cipher_color_type);
+            let decrypted = cipher.decrypt(ciphertext, &seed(1)).unwrap();
+
+            assert_eq!(decrypted.color(), plaintext.color());
+            let error = decrypted
+                .as_bytes()
+                .iter()
+                .zip(plaintext.as_bytes())
+                .map(|(a, b)| a.abs_diff(*b))
+                .max();
+            assert!(error <= Some(HyperChaosSVD::TOLERANCE));
+        }
+    }
+
+    #[test]
+    fn other_color_types_are_not_ciphertexts() {
+        let cipher = HyperChaosSVD::default();
+
+        for ciphertext in [image(8, 8), image_with_alpha(8, 8)] {
+            assert!(matches!(
+                cipher.decrypt(ciphertext, &seed(1)),
+                Err(CipherError::UnsupportedColorType(_))
+            ));
+        }
+    }
+}
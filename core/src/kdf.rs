@@ -0,0 +1,258 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use hmac::{Hmac, Mac};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::CipherError;
+
+/// Length of the random salt in bytes.
+pub const SALT_LEN: usize = 16;
+/// Length of a derived seed in bytes.
+pub const SEED_LEN: usize = 32;
+/// Length of a per-image nonce in bytes.
+pub const NONCE_LEN: usize = 32;
+
+/// Largest memory cost in KiB accepted from a header, 1 GiB.
+pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+/// Largest iteration count accepted from a header.
+pub const MAX_ITERATIONS: u32 = 16;
+/// Largest lane count accepted from a header.
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id settings used to turn a password into a [`Seed`].
+///
+/// They are not secret and have to be stored with the ciphertext, since decryption needs
+/// the exact same salt and costs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub salt: [u8; SALT_LEN],
+    /// Memory cost in KiB.
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Default costs with a fresh random salt.
+    pub fn generate() -> Result<Self, CipherError> {
+        let mut salt = [0; SALT_LEN];
+        getrandom::fill(&mut salt).map_err(|err| CipherError::Io(err.into()))?;
+
+        Ok(Self {
+            salt,
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        })
+    }
+
+    /// Rejects costs beyond [`MAX_MEMORY_KIB`], [`MAX_ITERATIONS`] and
+    /// [`MAX_PARALLELISM`]. Headers are read before anything is authenticated, and
+    /// deriving with the costs of a forged one could exhaust the memory or the CPU.
+    pub fn check(&self) -> Result<(), CipherError> {
+        if self.memory_kib > MAX_MEMORY_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(CipherError::InvalidHeader(format!(
+                "KDF costs of {} KiB, {} iterations and {} lanes exceed the limits",
+                self.memory_kib, self.iterations, self.parallelism
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs Argon2id over `key`.
+    pub fn derive(&self, key: &str) -> Result<Seed, CipherError> {
+        let invalid = |err: argon2::Error| CipherError::InvalidParameter(format!("KDF: {err}"));
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(SEED_LEN),
+        )
+        .map_err(invalid)?;
+
+        let mut seed = Seed([0; SEED_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(key.as_bytes(), &self.salt, &mut seed.0)
+            .map_err(invalid)?;
+
+        Ok(seed)
+    }
+}
+
+/// What the cipher parameters depend on besides the key.
+///
+/// The ciphers draw every parameter from the seed alone, so two plaintexts encrypted under
+/// the same seed that differ in one sample give ciphertexts that only differ where that
+/// sample is moved or diffused to, which is easy to exploit with chosen plaintexts. A
+/// nonce mixed into the seed instead changes every chaotic initial condition, and with it
+/// the whole keystream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum NonceMode {
+    /// The seed alone, as in the original designs. Diffusion feedback only runs forward,
+    /// so a change to one sample leaves every sample before it untouched, and the NPCR of
+    /// two plaintexts one sample apart is far below the ideal 99.6%.
+    #[default]
+    Key,
+    /// A random nonce, so encrypting the same image twice gives unrelated ciphertexts.
+    Random,
+    /// A keyed hash of the plaintext, so the same image and seed always give the same
+    /// ciphertext but any change to the image changes every parameter.
+    Plaintext,
+}
+
+impl NonceMode {
+    /// Nonce to encrypt `image` under `seed` with, none for [`NonceMode::Key`]. It is not
+    /// secret but has to be kept with the ciphertext.
+    pub fn nonce(
+        self,
+        image: &DynamicImage,
+        seed: &Seed,
+    ) -> Result<Option<[u8; NONCE_LEN]>, CipherError> {
+        let mut nonce = [0; NONCE_LEN];
+
+        match self {
+            Self::Key => return Ok(None),
+            Self::Random => {
+                getrandom::fill(&mut nonce).map_err(|err| CipherError::Io(err.into()))?
+            }
+            Self::Plaintext => {
+                // Keyed, so the nonce cannot be used to confirm a guess of the plaintext.
+                let digest =
+                    Hmac::<Sha256>::new_from_slice(seed.subkey("imaged.plaintext").as_slice())
+                        .expect("HMAC accepts any key length")
+                        .chain_update(image.width().to_le_bytes())
+                        .chain_update(image.height().to_le_bytes())
+                        .chain_update(format!("{:?}", image.color()).as_bytes())
+                        .chain_update(image.as_bytes())
+                        .finalize()
+                        .into_bytes();
+                nonce.copy_from_slice(&digest);
+            }
+        }
+
+        Ok(Some(nonce))
+    }
+}
+
+/// Secret output of the KDF that every cipher parameter is derived from. It is wiped
+/// when dropped.
+#[derive(Clone)]
+pub struct Seed([u8; SEED_LEN]);
+
+impl Drop for Seed {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Seed {
+    pub(crate) fn from_bytes(bytes: [u8; SEED_LEN]) -> Seed {
+        Seed(bytes)
+    }
+
+    /// Key for `purpose` that is independent of the cipher parameters,
+    /// `HMAC-SHA256(seed, purpose)`.
+    pub(crate) fn subkey(&self, purpose: &str) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(
+            Hmac::<Sha256>::new_from_slice(&self.0)
+                .expect("HMAC accepts any key length")
+                .chain_update(purpose.as_bytes())
+                .finalize()
+                .into_bytes()
+                .into(),
+        )
+    }
+
+    /// Independent seed for `purpose`, `HMAC-SHA256(seed, purpose)`.
+    pub(crate) fn subseed(&self, purpose: &str) -> Seed {
+        let mut seed = Seed([0; SEED_LEN]);
+        seed.0.copy_from_slice(self.subkey(purpose).as_slice());
+        seed
+    }
+
+    /// Seed for one image, `HMAC-SHA256(seed, "imaged.nonce" || nonce)`.
+    pub fn with_nonce(&self, nonce: &[u8; NONCE_LEN]) -> Seed {
+        let mut seed = Seed([0; SEED_LEN]);
+        seed.0.copy_from_slice(
+            &Hmac::<Sha256>::new_from_slice(&self.0)
+                .expect("HMAC accepts any key length")
+                .chain_update(b"imaged.nonce")
+                .chain_update(nonce)
+                .finalize()
+                .into_bytes(),
+        );
+        seed
+    }
+
+    /// Deterministic 64-bit value for the parameter `label`, taken from
+    /// `SHA-256(seed || label)`.
+    pub(crate) fn parameter(&self, label: &str) -> u64 {
+        let digest = Sha256::new()
+            .chain_update(self.0)
+            .chain_update(label.as_bytes())
+            .finalize();
+
+        u64::from_le_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
+    }
+
+    /// Deterministic value in `[0, 1)` for the parameter `label`.
+    pub(crate) fn unit(&self, label: &str) -> f64 {
+        (self.parameter(label) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+    use crate::analysis::differential;
+    use crate::testing::{image, seed};
+    use crate::{HenonMap, ImageCipher};
+
+    /// NPCR of every channel between the ciphertexts of two images one sample apart.
+    fn npcr(mode: NonceMode) -> Vec<f64> {
+        let a = image(64, 64);
+        let mut b = a.clone();
+        b.as_mut_rgb8().unwrap().get_pixel_mut(32, 32)[0] ^= 1;
+
+        let encrypt = |image: &DynamicImage| {
+            let nonce = mode.nonce(image, &seed(1)).unwrap();
+            let seed = nonce.map_or(seed(1), |nonce| seed(1).with_nonce(&nonce));
+            HenonMap::default().encrypt(image.clone(), &seed).unwrap()
+        };
+
+        differential(&encrypt(&a), &encrypt(&b))
+            .unwrap()
+            .iter()
+            .map(|channel| channel.npcr)
+            .collect()
+    }
+
+    #[test]
+    fn plaintext_nonce_spreads_a_one_sample_change() {
+        for npcr in npcr(NonceMode::Plaintext) {
+            assert!(npcr > 99.0, "NPCR {npcr}");
+        }
+    }
+
+    #[test]
+    fn key_alone_leaves_the_samples_before_a_change() {
+        for npcr in npcr(NonceMode::Key) {
+            assert!(npcr < 55.0, "NPCR {npcr}");
+        }
+    }
+
+    #[test]
+    fn plaintext_nonce_is_deterministic() {
+        let nonce = |image| NonceMode::Plaintext.nonce(&image, &seed(1)).unwrap();
+        assert_eq!(nonce(image(8, 8)), nonce(image(8, 8)));
+    }
+}
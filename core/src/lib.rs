@@ -1,72 +1,65 @@
-use image::{
-    DynamicImage, GenericImageView, Pixel, Rgb32FImage, RgbImage, Rgba, Rgba32FImage, RgbaImage,
-};
+use image::DynamicImage;
 
-pub trait ImageCipher {
-    fn hash(key: String) -> String;
-    fn encrypt(image: DynamicImage, key: String) -> DynamicImage;
-    fn decrypt(image: DynamicImage, key: String) -> DynamicImage;
-}
-
-pub enum CipherMethod {
-    ArnoldCat(ArnoldCat),
-    HenonMap(HenonMap),
-    HyperChaosSVD(HyperChaosSVD),
-}
-
-pub struct ArnoldCat;
-pub struct HenonMap;
-pub struct HyperChaosSVD;
-
-fn chaotic_sequence_generation(image: RgbaImage) {
-    let f = 2.7;
-    let g = -3;
-    let h = 10;
-    let k = 2;
-    let l = 100;
-    let m = 1;
-
-    let s_chaotic = 2;
-}
-
-impl ImageCipher for HyperChaosSVD {
-    fn hash(key: String) -> String {
-        key
-    }
+pub mod analysis;
+mod arnold;
+mod cat3d;
+mod chen;
+pub mod container;
+mod error;
+mod henon;
+mod hyperchaos;
+pub mod kdf;
+mod logistic;
+pub mod onedim;
+pub mod parallel;
+pub mod pipeline;
+mod pwlcm;
+pub mod registry;
+pub mod rng;
+mod samples;
+pub mod sbox;
+mod tent;
+#[cfg(test)]
+mod testing;
 
-    fn encrypt(image: DynamicImage, key: String) -> DynamicImage {
-        let dimensions = image.dimensions();
-        let rgba_image = image.to_rgba8();
+pub use arnold::ArnoldCat;
+pub use cat3d::CatMap3D;
+pub use chen::ChenSystem;
+pub use error::CipherError;
+pub use henon::HenonMap;
+pub use hyperchaos::HyperChaosSVD;
+pub use kdf::{KdfParams, NonceMode, Seed};
+pub use logistic::LogisticMap;
+pub use pipeline::Pipeline;
+pub use pwlcm::PiecewiseLinearMap;
+pub use tent::TentMap;
 
-        let red_channel = RgbaImage::from_fn(dimensions.0, dimensions.1, |x, y| {
-            let pixel = rgba_image.get_pixel(x, y);
-            Rgba([pixel[0], 0, 0, pixel[3]])
-        });
-        let green_channel = RgbaImage::from_fn(dimensions.0, dimensions.1, |x, y| {
-            let pixel = rgba_image.get_pixel(x, y);
-            Rgba([0, pixel[1], 0, pixel[3]])
-        });
-        let blue_channel = RgbaImage::from_fn(dimensions.0, dimensions.1, |x, y| {
-            let pixel = rgba_image.get_pixel(x, y);
-            Rgba([0, 0, pixel[2], pixel[3]])
-        });
-
-        red_channel.into()
-    }
-
-    fn decrypt(image: DynamicImage, key: String) -> DynamicImage {
-        image
+/// A configured image cipher.
+///
+/// Instances only hold non-secret settings; everything secret is drawn from the [`Seed`]
+/// at call time, so one instance can encrypt any number of images under different keys.
+/// Methods are looked up by name through the [`registry`].
+pub trait ImageCipher: Send + Sync {
+    /// Name of the method in the [`registry`].
+    fn name(&self) -> &'static str;
+    /// Settings of this instance as a JSON object, which [`registry::MethodInfo::build`]
+    /// accepts back.
+    fn params(&self) -> serde_json::Value;
+    /// Derives the seed that all cipher parameters are drawn from.
+    fn hash(&self, key: &str, kdf: &KdfParams) -> Result<Seed, CipherError> {
+        kdf.derive(key)
     }
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError>;
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError>;
 }
 
-impl ImageCipher for ArnoldCat {
-    fn hash(key: String) -> String {
-        key
-    }
-    fn encrypt(image: DynamicImage, key: String) -> DynamicImage {
-        image
-    }
-    fn decrypt(image: DynamicImage, key: String) -> DynamicImage {
-        image
+/// Rejects images without any pixels, which no cipher can work on.
+pub(crate) fn check_dimensions(image: &DynamicImage) -> Result<(), CipherError> {
+    if image.width() == 0 || image.height() == 0 {
+        return Err(CipherError::InvalidDimensions {
+            width: image.width(),
+            height: image.height(),
+        });
     }
+    Ok(())
 }
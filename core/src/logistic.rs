@@ -0,0 +1,124 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::onedim::{self, Map};
+use crate::{CipherError, ImageCipher, Seed, registry};
+
+/// Logistic map cipher, in the [permutation-diffusion scheme](crate::onedim) of the
+/// one-dimensional maps.
+///
+/// The orbits follow `x' = r*x*(1 - x)`, with a key-derived `r` in `[3.99, 4)` next to the
+/// fully chaotic `r = 4`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogisticMap {
+    /// Iterations dropped before each orbit is used.
+    pub transient: u32,
+}
+
+impl Default for LogisticMap {
+    fn default() -> Self {
+        Self {
+            transient: onedim::DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+/// Parameters and state of the map, see [`MapOrbit`](crate::rng::MapOrbit).
+pub struct Logistic {
+    r: f64,
+    x: f64,
+}
+
+impl Map for Logistic {
+    const NAME: &'static str = "logistic";
+
+    fn new(unit: impl Fn(&str) -> f64) -> Self {
+        Self {
+            // Rounding would take units just below 1 to 4 itself.
+            r: (3.99 + 0.01 * unit("r")).min(4.0f64.next_down()),
+            x: 0.01 + 0.98 * unit("x0"),
+        }
+    }
+
+    fn step(&mut self) -> f64 {
+        self.x = self.r * self.x * (1.0 - self.x);
+        self.x
+    }
+}
+
+impl Drop for Logistic {
+    fn drop(&mut self) {
+        self.r.zeroize();
+        self.x.zeroize();
+    }
+}
+
+impl ImageCipher for LogisticMap {
+    fn name(&self) -> &'static str {
+        "logistic-map"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::encrypt::<Logistic>(image, seed, self.transient)
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::decrypt::<Logistic>(image, seed, self.transient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onedim::MapOrbit;
+    use crate::rng::Orbit;
+    use crate::testing::seed;
+
+    /// Largest value below 1 that [`Seed::unit`] can return.
+    const UNIT_MAX: f64 = 1.0 - f64::EPSILON / 2.0;
+
+    fn drawn(byte: u8) -> Logistic {
+        Logistic::new(|name| seed(byte).unit(&format!("logistic.{name}.0")))
+    }
+
+    #[test]
+    fn parameters_stay_in_the_chaotic_range() {
+        let maps = [Logistic::new(|_| 0.0), Logistic::new(|_| UNIT_MAX)]
+            .into_iter()
+            .chain((0..=255).map(drawn));
+
+        for map in maps {
+            assert!((3.99..4.0).contains(&map.r), "r = {}", map.r);
+            assert!(map.x > 0.0 && map.x < 1.0);
+        }
+    }
+
+    #[test]
+    fn step_stays_in_the_unit_interval() {
+        for byte in 0..16 {
+            let mut map = drawn(byte);
+            for _ in 0..100_000 {
+                let x = map.step();
+                assert!((0.0..1.0).contains(&x), "x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_point_is_reseeded() {
+        let mut orbit = MapOrbit::<Logistic>::new(&seed(1), "logistic", Logistic::TRANSIENT);
+        orbit.force(|map| {
+            map.x = 1.0 - 1.0 / map.r;
+            map.x
+        });
+
+        orbit.next_u32();
+        assert_eq!(orbit.attempt(), 1);
+    }
+}
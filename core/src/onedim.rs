@@ -0,0 +1,246 @@
+//! Permutation-diffusion scheme shared by the ciphers driven by a single chaotic orbit: the
+//! one-dimensional maps, and the Chen system with its state quantized into `[0, 1)`.
+//!
+//! A first key-seeded orbit is turned into a permutation of the pixels, and a second one
+//! into a keystream that diffuses the permuted samples with ciphertext feedback,
+//! `c[i] = ((p[i] + c[i - 1]) mod 256) ^ k[i]`, as in [`HenonMap`](crate::HenonMap).
+//! Decryption undoes the diffusion in parallel and then the permutation. Both stages take
+//! any random number generator, the ciphers use [`ChaoticRng`]s of their map.
+
+use image::DynamicImage;
+use rand_core::RngCore;
+use rayon::prelude::*;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::registry::{self, ParamSpec};
+use crate::rng::{ChaoticRng, CycleCheck, Orbit};
+use crate::samples::Samples;
+use crate::{CipherError, Seed};
+
+pub(crate) const DEFAULT_TRANSIENT: u32 = 1000;
+
+pub(crate) const PARAMS: &[ParamSpec] = &[registry::transient_param(DEFAULT_TRANSIENT)];
+
+/// A chaotic map of the unit interval, or a system quantized into one.
+pub trait Map {
+    /// Prefix of the seed labels the ciphers draw the map from.
+    const NAME: &'static str;
+    /// Steps a [`ChaoticRng`] made with [`rand_core::SeedableRng::from_seed`] discards.
+    const TRANSIENT: u32 = DEFAULT_TRANSIENT;
+
+    /// Draws the control parameters, kept inside the chaotic range, and the initial value
+    /// from `unit`, which gives a key-derived value in `[0, 1)` for each label.
+    fn new(unit: impl Fn(&str) -> f64) -> Self;
+
+    /// Advances the map and returns the new value.
+    fn step(&mut self) -> f64;
+}
+
+/// Key-seeded orbit of the map `M`.
+///
+/// In floating point every orbit eventually falls into a cycle or onto a fixed point at the
+/// edge of the interval, so whenever it leaves `(0, 1)` or returns to the last checkpoint
+/// it is reseeded with the next set of key-derived parameters.
+pub struct MapOrbit<M: Map> {
+    seed: Seed,
+    label: String,
+    transient: u32,
+    attempt: u32,
+    map: M,
+    x: f64,
+    cycle: CycleCheck,
+}
+
+impl<M: Map> MapOrbit<M> {
+    fn draw(seed: &Seed, label: &str, attempt: u32) -> M {
+        M::new(|name| seed.unit(&format!("{label}.{name}.{attempt}")))
+    }
+
+    fn reseed(&mut self) {
+        self.attempt += 1;
+        self.map = Self::draw(&self.seed, &self.label, self.attempt);
+        self.settle();
+    }
+
+    /// Discards the transient of a freshly drawn map.
+    fn settle(&mut self) {
+        for _ in 0..self.transient {
+            self.x = self.map.step();
+        }
+        self.cycle = CycleCheck::new(self.x);
+    }
+
+    fn next(&mut self) -> f64 {
+        loop {
+            self.x = self.map.step();
+
+            if !(self.x > 0.0 && self.x < 1.0) || self.cycle.is_cycling(self.x) {
+                self.reseed();
+                continue;
+            }
+            return self.x;
+        }
+    }
+}
+
+impl<M: Map> Orbit for MapOrbit<M> {
+    const TRANSIENT: u32 = M::TRANSIENT;
+
+    fn new(seed: &Seed, label: &str, transient: u32) -> Self {
+        let mut orbit = Self {
+            seed: seed.clone(),
+            label: label.to_string(),
+            transient,
+            attempt: 0,
+            map: Self::draw(seed, label, 0),
+            x: 0.0,
+            cycle: CycleCheck::new(f64::NAN),
+        };
+        orbit.settle();
+        orbit
+    }
+
+    /// Low 32 bits of the value scaled by `10^14`, where the digits are well mixed.
+    fn next_u32(&mut self) -> u32 {
+        (self.next() * 1e14) as u64 as u32
+    }
+}
+
+#[cfg(test)]
+impl<M: Map> MapOrbit<M> {
+    /// Puts the map into the state `edit` gives it, and the orbit on the value `edit`
+    /// returns, as if it had just settled there.
+    pub(crate) fn force(&mut self, edit: impl FnOnce(&mut M) -> f64) {
+        self.x = edit(&mut self.map);
+        self.cycle = CycleCheck::new(self.x);
+    }
+
+    /// Number of times the orbit has been reseeded.
+    pub(crate) fn attempt(&self) -> u32 {
+        self.attempt
+    }
+}
+
+impl<M: Map> Drop for MapOrbit<M> {
+    fn drop(&mut self) {
+        self.x.zeroize();
+    }
+}
+
+/// Order of the pixels after encryption: pixel `i` of the ciphertext is pixel
+/// `permutation[i]` of the plaintext. It sorts random keys, so it is a bijection whatever
+/// values `rng` produces.
+pub(crate) fn permutation(rng: &mut impl RngCore, len: usize) -> Zeroizing<Vec<usize>> {
+    let keys = Zeroizing::new((0..len).map(|_| rng.next_u64()).collect::<Vec<u64>>());
+
+    let mut order = Zeroizing::new((0..len).collect::<Vec<usize>>());
+    order.par_sort_unstable_by_key(|&i| (keys[i], i));
+    order
+}
+
+/// Moves every pixel of `pixel_len` bytes of `samples` to its place in the permutation
+/// drawn from `rng`.
+pub(crate) fn permute(samples: &[u8], pixel_len: usize, rng: &mut impl RngCore) -> Vec<u8> {
+    let permutation = permutation(rng, samples.len() / pixel_len);
+    let mut buffer = vec![0; samples.len()];
+    buffer
+        .par_chunks_mut(pixel_len)
+        .zip(permutation.par_iter())
+        .for_each(|(pixel, &source)| {
+            pixel.copy_from_slice(&samples[source * pixel_len..][..pixel_len]);
+        });
+    buffer
+}
+
+/// Reverses [`permute`] given an `rng` in the same state.
+pub(crate) fn unpermute(samples: &[u8], pixel_len: usize, rng: &mut impl RngCore) -> Vec<u8> {
+    let permutation = permutation(rng, samples.len() / pixel_len);
+    let mut buffer = vec![0; samples.len()];
+    for (pixel, &target) in samples.chunks_exact(pixel_len).zip(permutation.iter()) {
+        buffer[target * pixel_len..][..pixel_len].copy_from_slice(pixel);
+    }
+    buffer
+}
+
+/// Diffuses `samples` in place with a keystream from `rng` and ciphertext feedback,
+/// starting from `iv`.
+pub(crate) fn diffuse(samples: &mut [u8], rng: &mut impl RngCore, iv: u8) {
+    let mut keystream = Zeroizing::new(vec![0; samples.len()]);
+    rng.fill_bytes(&mut keystream);
+
+    let mut previous = iv;
+    for (sample, k) in samples.iter_mut().zip(keystream.iter()) {
+        *sample = sample.wrapping_add(previous) ^ k;
+        previous = *sample;
+    }
+}
+
+/// Feedback value for the first sample of a diffusion labelled `label`.
+pub(crate) fn iv(seed: &Seed, label: &str) -> u8 {
+    seed.parameter(&format!("{label}.iv")) as u8
+}
+
+/// Reverses [`diffuse`] given an `rng` in the same state. Every sample only depends on the
+/// previous ciphertext sample, so this runs in parallel.
+pub(crate) fn undiffuse(samples: &[u8], rng: &mut impl RngCore, iv: u8) -> Vec<u8> {
+    let mut keystream = Zeroizing::new(vec![0; samples.len()]);
+    rng.fill_bytes(&mut keystream);
+
+    (0..samples.len())
+        .into_par_iter()
+        .map(|i| {
+            let previous = if i == 0 { iv } else { samples[i - 1] };
+            (samples[i] ^ keystream[i]).wrapping_sub(previous)
+        })
+        .collect()
+}
+
+/// Generators for the permutation and the diffusion of `M`.
+struct Generators<M: Map> {
+    permutation: ChaoticRng<MapOrbit<M>>,
+    diffusion: ChaoticRng<MapOrbit<M>>,
+    /// Feedback value for the first sample.
+    iv: u8,
+}
+
+impl<M: Map> Generators<M> {
+    fn new(seed: &Seed, transient: u32) -> Self {
+        Self {
+            permutation: ChaoticRng::new(seed, &format!("{}.permutation", M::NAME), transient),
+            diffusion: ChaoticRng::new(seed, &format!("{}.diffusion", M::NAME), transient),
+            iv: iv(seed, M::NAME),
+        }
+    }
+}
+
+pub(crate) fn encrypt<M: Map>(
+    image: DynamicImage,
+    seed: &Seed,
+    transient: u32,
+) -> Result<DynamicImage, CipherError> {
+    let mut samples = Samples::new(image)?;
+    let mut generators = Generators::<M>::new(seed, transient);
+
+    samples.bytes = permute(
+        &samples.bytes,
+        samples.pixel_len(),
+        &mut generators.permutation,
+    );
+    diffuse(&mut samples.bytes, &mut generators.diffusion, generators.iv);
+
+    Ok(samples.into_image())
+}
+
+pub(crate) fn decrypt<M: Map>(
+    image: DynamicImage,
+    seed: &Seed,
+    transient: u32,
+) -> Result<DynamicImage, CipherError> {
+    let mut samples = Samples::new(image)?;
+    let mut generators = Generators::<M>::new(seed, transient);
+
+    let permuted = undiffuse(&samples.bytes, &mut generators.diffusion, generators.iv);
+    samples.bytes = unpermute(&permuted, samples.pixel_len(), &mut generators.permutation);
+
+    Ok(samples.into_image())
+}
@@ -0,0 +1,60 @@
+//! Thread pool the ciphers run on.
+//!
+//! Ciphers split their work with rayon over rows, samples or channels, so they run on
+//! whichever pool is current: the global one, with one worker per CPU, or a pool with a
+//! fixed number of workers inside [`with_threads`]. The work is always split the same way
+//! and each part is computed on its own, so the output does not depend on the thread count.
+//!
+//! Not every step scales with the pool. Chaotic orbits are generated one value after the
+//! other, and diffusion with ciphertext feedback needs every previous ciphertext sample
+//! during encryption, so [`HenonMap`](crate::HenonMap) encryption and the diffusion stage
+//! of the one-dimensional ciphers and pipelines run on a single thread. Their decryption
+//! and the permutations, substitutions, cat maps and SVD channels are the parts that spread
+//! over the workers.
+
+use rayon::ThreadPoolBuilder;
+
+use crate::CipherError;
+
+/// Runs `work` on a dedicated pool of `threads` workers, or one per CPU for `0`.
+pub fn with_threads<R: Send>(
+    threads: usize,
+    work: impl FnOnce() -> R + Send,
+) -> Result<R, CipherError> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|err| CipherError::InvalidParameter(format!("threads: {err}")))?;
+
+    Ok(pool.install(work))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+    use crate::testing::{image, seed};
+
+    #[test]
+    fn output_does_not_depend_on_the_thread_count() {
+        let plaintext = image(64, 48);
+
+        for info in registry::methods() {
+            let cipher = info.default_cipher();
+            let encrypt = |threads| {
+                with_threads(threads, || cipher.encrypt(plaintext.clone(), &seed(1))).unwrap()
+            };
+            let ciphertext = encrypt(1).unwrap();
+            assert_eq!(ciphertext, encrypt(8).unwrap(), "{}", info.name);
+
+            let decrypt =
+                |threads| with_threads(threads, || cipher.decrypt(ciphertext.clone(), &seed(1)));
+            assert_eq!(
+                decrypt(1).unwrap().unwrap(),
+                decrypt(8).unwrap().unwrap(),
+                "{}",
+                info.name
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Ciphers assembled from permutation, substitution and diffusion stages.
+//!
+//! A [`Pipeline`] runs its stages in order for a number of rounds, and decryption runs the
+//! inverse of every stage in reverse order. Every stage of every round draws its parameters
+//! from its own sub-seed of the key, so repeating a stage does not repeat its permutation
+//! or keystream. The definition is plain data and serializes to JSON, as in
+//!
+//! ```json
+//! {
+//!   "rounds": 2,
+//!   "stages": [
+//!     { "type": "arnold_cat", "max_iterations": 16 },
+//!     { "type": "diffusion", "generator": "henon" }
+//!   ]
+//! }
+//! ```
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::cat3d::Cat3DParams;
+use crate::registry::{self, ParamKind, ParamSpec};
+use crate::rng::Generator;
+use crate::samples::Samples;
+use crate::sbox::SBox;
+use crate::{CipherError, ImageCipher, Seed, arnold, onedim};
+
+/// A confusion or diffusion step of a [`Pipeline`] over the samples of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Stage {
+    /// Pixel permutation with the map of [`ArnoldCat`](crate::ArnoldCat).
+    ArnoldCat { max_iterations: u32 },
+    /// Permutation of samples across pixels and channels with the map of
+    /// [`CatMap3D`](crate::CatMap3D).
+    #[serde(rename = "cat_map_3d")]
+    CatMap3D { max_iterations: u32 },
+    /// Pixel permutation sorted from the outputs of a chaotic generator.
+    Permutation { generator: Generator },
+    /// Substitution of every sample with an S-box sorted from a chaotic generator.
+    Substitution { generator: Generator },
+    /// Diffusion with a keystream from a chaotic generator and ciphertext feedback,
+    /// `c[i] = ((p[i] + c[i - 1]) mod 256) ^ k[i]`.
+    Diffusion { generator: Generator },
+}
+
+impl Stage {
+    fn check(&self) -> Result<(), CipherError> {
+        match *self {
+            Self::ArnoldCat { max_iterations } | Self::CatMap3D { max_iterations } => {
+                arnold::check_max_iterations(max_iterations)
+            }
+            Self::Permutation { .. } | Self::Substitution { .. } | Self::Diffusion { .. } => Ok(()),
+        }
+    }
+
+    fn encrypt(&self, mut samples: Samples, seed: &Seed) -> Samples {
+        samples.bytes = match *self {
+            Self::ArnoldCat { max_iterations } => arnold::permute(&samples, seed, max_iterations),
+            Self::CatMap3D { max_iterations } => {
+                Self::cat3d(&samples, seed, max_iterations).permute(&samples)
+            }
+            Self::Permutation { generator } => onedim::permute(
+                &samples.bytes,
+                samples.pixel_len(),
+                &mut generator.rng(seed, "permutation"),
+            ),
+            Self::Substitution { generator } => {
+                SBox::new(seed, "sbox", generator).substitute(&mut samples.bytes);
+                return samples;
+            }
+            Self::Diffusion { generator } => {
+                onedim::diffuse(
+                    &mut samples.bytes,
+                    &mut generator.rng(seed, "diffusion"),
+                    onedim::iv(seed, "diffusion"),
+                );
+                return samples;
+            }
+        };
+        samples
+    }
+
+    fn decrypt(&self, mut samples: Samples, seed: &Seed) -> Samples {
+        samples.bytes = match *self {
+            Self::ArnoldCat { max_iterations } => arnold::unpermute(&samples, seed, max_iterations),
+            Self::CatMap3D { max_iterations } => {
+                Self::cat3d(&samples, seed, max_iterations).unpermute(&samples)
+            }
+            Self::Permutation { generator } => onedim::unpermute(
+                &samples.bytes,
+                samples.pixel_len(),
+                &mut generator.rng(seed, "permutation"),
+            ),
+            Self::Substitution { generator } => {
+                SBox::new(seed, "sbox", generator).unsubstitute(&mut samples.bytes);
+                return samples;
+            }
+            Self::Diffusion { generator } => onedim::undiffuse(
+                &samples.bytes,
+                &mut generator.rng(seed, "diffusion"),
+                onedim::iv(seed, "diffusion"),
+            ),
+        };
+        samples
+    }
+
+    fn cat3d(samples: &Samples, seed: &Seed, max_iterations: u32) -> Cat3DParams {
+        Cat3DParams::from_seed(
+            seed,
+            "cat3d",
+            samples.width,
+            samples.height,
+            samples.channels(),
+            max_iterations,
+        )
+    }
+}
+
+/// Rounds of ordered stages, each with its own sub-key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pipeline {
+    pub rounds: u32,
+    pub stages: Vec<Stage>,
+}
+
+const DEFAULT_ROUNDS: u32 = 2;
+/// Most stages a pipeline may have. Definitions come from untrusted headers, so the work a
+/// pipeline describes has to stay bounded, like its rounds.
+pub const MAX_STAGES: usize = 16;
+
+pub(crate) const PARAMS: &[ParamSpec] = &[ParamSpec {
+    name: "rounds",
+    description: "Times the stages are applied, each time with new sub-keys",
+    kind: ParamKind::Integer {
+        min: 1,
+        max: 16,
+        default: DEFAULT_ROUNDS as u64,
+    },
+}];
+
+/// An Arnold cat map permutation followed by Hénon map diffusion.
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            rounds: DEFAULT_ROUNDS,
+            stages: vec![
+                Stage::ArnoldCat {
+                    max_iterations: arnold::DEFAULT_MAX_ITERATIONS,
+                },
+                Stage::Diffusion {
+                    generator: Generator::Henon,
+                },
+            ],
+        }
+    }
+}
+
+impl Pipeline {
+    /// Checks the rounds, and the stages, which the parameter specs of the registry do not
+    /// describe. It runs on every call as well, since a pipeline built directly bypasses
+    /// the registry, and without rounds or stages it would return the plaintext.
+    pub(crate) fn check(&self) -> Result<(), CipherError> {
+        PARAMS[0].check(&serde_json::Value::from(self.rounds))?;
+        if self.stages.is_empty() {
+            return Err(CipherError::InvalidParameter(
+                "a pipeline needs at least one stage".to_string(),
+            ));
+        }
+        if self.stages.len() > MAX_STAGES {
+            return Err(CipherError::InvalidParameter(format!(
+                "a pipeline has at most {MAX_STAGES} stages"
+            )));
+        }
+        self.stages.iter().try_for_each(Stage::check)
+    }
+
+    fn stage_seed(seed: &Seed, round: u32, index: usize) -> Seed {
+        seed.subseed(&format!("imaged.pipeline.{round}.{index}"))
+    }
+}
+
+impl ImageCipher for Pipeline {
+    fn name(&self) -> &'static str {
+        "pipeline"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        self.check()?;
+        let mut samples = Samples::new(image)?;
+
+        for round in 0..self.rounds {
+            for (index, stage) in self.stages.iter().enumerate() {
+                samples = stage.encrypt(samples, &Self::stage_seed(seed, round, index));
+            }
+        }
+
+        Ok(samples.into_image())
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        self.check()?;
+        let mut samples = Samples::new(image)?;
+
+        for round in (0..self.rounds).rev() {
+            for (index, stage) in self.stages.iter().enumerate().rev() {
+                samples = stage.decrypt(samples, &Self::stage_seed(seed, round, index));
+            }
+        }
+
+        Ok(samples.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::registry;
+    use crate::testing::{image, seed};
+
+    #[test]
+    fn decrypt_restores_the_image() {
+        let plaintext = image(40, 30);
+        let pipeline = registry::lookup("pipeline").unwrap().build(&json!({
+            "stages": [
+                { "type": "cat_map_3d", "max_iterations": 4 },
+                { "type": "permutation", "generator": "tent" },
+                { "type": "substitution", "generator": "logistic" },
+                { "type": "diffusion", "generator": "chen" },
+            ]
+        }));
+        let pipeline = pipeline.unwrap();
+
+        let ciphertext = pipeline.encrypt(plaintext.clone(), &seed(1)).unwrap();
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(pipeline.decrypt(ciphertext, &seed(1)).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn stage_count_is_bounded() {
+        let stages = |count| json!({ "stages": vec![json!({ "type": "diffusion", "generator": "henon" }); count] });
+        let build = |count| registry::lookup("pipeline").unwrap().build(&stages(count));
+
+        assert!(build(MAX_STAGES).is_ok());
+        assert!(matches!(
+            build(MAX_STAGES + 1),
+            Err(CipherError::InvalidParameter(_))
+        ));
+        assert!(build(0).is_err());
+    }
+
+    #[test]
+    fn direct_pipelines_are_checked() {
+        let stage = Stage::ArnoldCat {
+            max_iterations: arnold::DEFAULT_MAX_ITERATIONS,
+        };
+        let invalid = [
+            Pipeline {
+                rounds: 0,
+                stages: vec![stage],
+            },
+            Pipeline {
+                rounds: DEFAULT_ROUNDS,
+                stages: vec![],
+            },
+            Pipeline {
+                rounds: DEFAULT_ROUNDS,
+                stages: vec![Stage::ArnoldCat { max_iterations: 0 }],
+            },
+        ];
+
+        for pipeline in invalid {
+            assert!(matches!(
+                pipeline.encrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+            assert!(matches!(
+                pipeline.decrypt(image(8, 8), &seed(1)),
+                Err(CipherError::InvalidParameter(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::onedim::{self, Map};
+use crate::{CipherError, ImageCipher, Seed, registry};
+
+/// Piecewise linear chaotic map (PWLCM) permutation-diffusion cipher.
+///
+/// The map is `x' = x/p` on `[0, p)`, `(x - p)/(0.5 - p)` on `[p, 0.5)` and mirrored on
+/// `[0.5, 1]`, with a key-derived control parameter `p` in `[0.05, 0.45)`. It is chaotic
+/// with a uniform invariant density for every `p` in `(0, 0.5)`. Its orbits drive the
+/// [permutation-diffusion scheme](crate::onedim) of the one-dimensional maps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PiecewiseLinearMap {
+    /// Iterations dropped before each orbit is used.
+    pub transient: u32,
+}
+
+impl Default for PiecewiseLinearMap {
+    fn default() -> Self {
+        Self {
+            transient: onedim::DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+/// Parameters and state of the map, see [`MapOrbit`](crate::rng::MapOrbit).
+pub struct Pwlcm {
+    p: f64,
+    x: f64,
+}
+
+impl Map for Pwlcm {
+    const NAME: &'static str = "pwlcm";
+
+    fn new(unit: impl Fn(&str) -> f64) -> Self {
+        Self {
+            p: 0.05 + 0.4 * unit("p"),
+            x: 0.01 + 0.98 * unit("x0"),
+        }
+    }
+
+    fn step(&mut self) -> f64 {
+        let x = self.x.min(1.0 - self.x);
+        self.x = if x < self.p {
+            x / self.p
+        } else {
+            (x - self.p) / (0.5 - self.p)
+        };
+        self.x
+    }
+}
+
+impl Drop for Pwlcm {
+    fn drop(&mut self) {
+        self.p.zeroize();
+        self.x.zeroize();
+    }
+}
+
+impl ImageCipher for PiecewiseLinearMap {
+    fn name(&self) -> &'static str {
+        "pwlcm"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::encrypt::<Pwlcm>(image, seed, self.transient)
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::decrypt::<Pwlcm>(image, seed, self.transient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onedim::MapOrbit;
+    use crate::rng::Orbit;
+    use crate::testing::seed;
+
+    /// Largest value below 1 that [`Seed::unit`] can return.
+    const UNIT_MAX: f64 = 1.0 - f64::EPSILON / 2.0;
+
+    fn drawn(byte: u8) -> Pwlcm {
+        Pwlcm::new(|name| seed(byte).unit(&format!("pwlcm.{name}.0")))
+    }
+
+    #[test]
+    fn parameters_stay_in_the_chaotic_range() {
+        let maps = [Pwlcm::new(|_| 0.0), Pwlcm::new(|_| UNIT_MAX)]
+            .into_iter()
+            .chain((0..=255).map(drawn));
+
+        for map in maps {
+            assert!((0.05..0.45).contains(&map.p), "p = {}", map.p);
+            assert!(map.x > 0.0 && map.x < 1.0);
+        }
+    }
+
+    #[test]
+    fn step_stays_in_the_unit_interval() {
+        for byte in 0..16 {
+            let mut map = drawn(byte);
+            for _ in 0..100_000 {
+                let x = map.step();
+                assert!((0.0..1.0).contains(&x), "x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_point_is_reseeded() {
+        let mut orbit = MapOrbit::<Pwlcm>::new(&seed(1), "pwlcm", Pwlcm::TRANSIENT);
+        orbit.force(|map| {
+            map.x = (1.0 - map.p) / (1.5 - map.p);
+            map.x
+        });
+
+        orbit.next_u32();
+        assert_eq!(orbit.attempt(), 1);
+    }
+}
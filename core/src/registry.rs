@@ -0,0 +1,260 @@
+//! Every cipher method by name, with the settings it accepts.
+//!
+//! Frontends list methods and their parameters from here instead of keeping their own
+//! enums, and the container uses it to rebuild the cipher recorded in a header.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    ArnoldCat, CatMap3D, ChenSystem, CipherError, HenonMap, HyperChaosSVD, ImageCipher,
+    LogisticMap, PiecewiseLinearMap, Pipeline, TentMap, arnold, chen, onedim, pipeline,
+};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamKind {
+    Integer { min: u64, max: u64, default: u64 },
+    Float { min: f64, max: f64, default: f64 },
+}
+
+/// A non-secret setting of a method.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(flatten)]
+    pub kind: ParamKind,
+}
+
+impl ParamSpec {
+    /// Parses `value` as this parameter, for frontends that take settings as text.
+    pub fn parse(&self, value: &str) -> Result<Value, CipherError> {
+        let invalid = || CipherError::InvalidParameter(format!("{} = {value}", self.name));
+
+        match self.kind {
+            ParamKind::Integer { .. } => {
+                value.parse::<u64>().map(Value::from).map_err(|_| invalid())
+            }
+            ParamKind::Float { .. } => value.parse::<f64>().map(Value::from).map_err(|_| invalid()),
+        }
+    }
+
+    pub(crate) fn check(&self, value: &Value) -> Result<(), CipherError> {
+        let in_range = match self.kind {
+            ParamKind::Integer { min, max, .. } => value
+                .as_u64()
+                .is_some_and(|value| (min..=max).contains(&value)),
+            ParamKind::Float { min, max, .. } => value
+                .as_f64()
+                .is_some_and(|value| (min..=max).contains(&value)),
+        };
+
+        if in_range {
+            Ok(())
+        } else {
+            Err(CipherError::InvalidParameter(format!(
+                "{} = {value} is out of range",
+                self.name
+            )))
+        }
+    }
+}
+
+/// Spec of the `transient` setting of the ciphers driven by chaotic orbits, which only
+/// differ in its default.
+pub(crate) const fn transient_param(default: u32) -> ParamSpec {
+    ParamSpec {
+        name: "transient",
+        description: "Steps of the chaotic orbits discarded before they are used",
+        kind: ParamKind::Integer {
+            min: 100,
+            max: 100_000,
+            default: default as u64,
+        },
+    }
+}
+
+/// Settings of `cipher` as a JSON object, for [`ImageCipher::params`].
+pub(crate) fn to_params(cipher: &impl Serialize) -> Value {
+    serde_json::to_value(cipher).expect("parameters serialize to JSON")
+}
+
+#[derive(Debug, Serialize)]
+pub struct MethodInfo {
+    /// Stable identifier, used on the command line and in container headers.
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamSpec],
+    #[serde(skip)]
+    build: fn(&Value) -> Result<Box<dyn ImageCipher>, CipherError>,
+}
+
+impl MethodInfo {
+    /// Configures the method from a JSON object of parameters. Missing parameters take
+    /// their defaults, `null` means all defaults.
+    pub fn build(&self, params: &Value) -> Result<Box<dyn ImageCipher>, CipherError> {
+        (self.build)(params)
+    }
+
+    pub fn default_cipher(&self) -> Box<dyn ImageCipher> {
+        self.build(&Value::Null)
+            .expect("default parameters are in range")
+    }
+
+    fn check(&self, params: &Value) -> Result<(), CipherError> {
+        self.params
+            .iter()
+            .try_for_each(|spec| spec.check(&params[spec.name]))
+    }
+}
+
+static METHODS: [MethodInfo; 9] = [
+    MethodInfo {
+        name: "arnold-cat",
+        title: "Arnold cat map",
+        description: "Pixel permutation with a key-derived generalized cat map",
+        params: arnold::PARAMS,
+        build: build::<ArnoldCat>,
+    },
+    MethodInfo {
+        name: "henon-map",
+        title: "Hénon map",
+        description: "Hénon map keystream with ciphertext feedback diffusion",
+        params: onedim::PARAMS,
+        build: build::<HenonMap>,
+    },
+    MethodInfo {
+        name: "hyper-chaos-svd",
+        title: "Hyperchaotic SVD",
+        description: "SVD scrambling driven by the hyperchaotic Chen system (8-bit images only, floating-point output)",
+        params: chen::PARAMS,
+        build: build::<HyperChaosSVD>,
+    },
+    MethodInfo {
+        name: "logistic-map",
+        title: "Logistic map",
+        description: "Logistic map pixel permutation and keystream diffusion",
+        params: onedim::PARAMS,
+        build: build::<LogisticMap>,
+    },
+    MethodInfo {
+        name: "tent-map",
+        title: "Tent map",
+        description: "Tent map pixel permutation and keystream diffusion",
+        params: onedim::PARAMS,
+        build: build::<TentMap>,
+    },
+    MethodInfo {
+        name: "pwlcm",
+        title: "Piecewise linear chaotic map",
+        description: "Piecewise linear chaotic map pixel permutation and keystream diffusion",
+        params: onedim::PARAMS,
+        build: build::<PiecewiseLinearMap>,
+    },
+    MethodInfo {
+        name: "chen-system",
+        title: "Hyperchaotic Chen system",
+        description: "Hyperchaotic Chen system (RK4) pixel permutation and keystream diffusion",
+        params: chen::PARAMS,
+        build: build::<ChenSystem>,
+    },
+    MethodInfo {
+        name: "cat-map-3d",
+        title: "3-D cat map",
+        description: "Sample permutation across pixels and color channels with a 3-D cat map",
+        params: arnold::PARAMS,
+        build: build::<CatMap3D>,
+    },
+    MethodInfo {
+        name: "pipeline",
+        title: "Pipeline",
+        description: "Rounds of permutation and diffusion stages, each with its own sub-key",
+        params: pipeline::PARAMS,
+        build: build_pipeline,
+    },
+];
+
+pub fn methods() -> &'static [MethodInfo] {
+    &METHODS
+}
+
+pub fn lookup(name: &str) -> Option<&'static MethodInfo> {
+    METHODS.iter().find(|method| method.name == name)
+}
+
+fn build<C>(params: &Value) -> Result<Box<dyn ImageCipher>, CipherError>
+where
+    C: ImageCipher + Default + Serialize + DeserializeOwned + 'static,
+{
+    Ok(Box::new(parse::<C>(params)?))
+}
+
+fn build_pipeline(params: &Value) -> Result<Box<dyn ImageCipher>, CipherError> {
+    let pipeline = parse::<Pipeline>(params)?;
+    pipeline.check()?;
+    Ok(Box::new(pipeline))
+}
+
+/// Deserializes the settings of a cipher and checks them against its specs.
+fn parse<C>(params: &Value) -> Result<C, CipherError>
+where
+    C: ImageCipher + Default + DeserializeOwned,
+{
+    let cipher = match params {
+        Value::Null => C::default(),
+        params => serde_json::from_value(params.clone())
+            .map_err(|err| CipherError::InvalidParameter(err.to_string()))?,
+    };
+
+    let info = lookup(cipher.name()).expect("every cipher is registered");
+    info.check(&cipher.params())?;
+
+    Ok(cipher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{deep_image, float_image_with_alpha, seed};
+
+    #[test]
+    fn deep_samples_round_trip_bit_for_bit() {
+        for method in methods()
+            .iter()
+            .filter(|method| method.name != "hyper-chaos-svd")
+        {
+            let cipher = method.default_cipher();
+
+            for plaintext in [deep_image(19, 11), float_image_with_alpha(11, 19)] {
+                let ciphertext = cipher.encrypt(plaintext.clone(), &seed(1)).unwrap();
+                assert_eq!(ciphertext.color(), plaintext.color());
+                assert_ne!(ciphertext.as_bytes(), plaintext.as_bytes());
+
+                // Compared as bytes, since NaN samples never compare equal as floats.
+                let decrypted = cipher.decrypt(ciphertext, &seed(1)).unwrap();
+                assert_eq!(decrypted.color(), plaintext.color(), "{}", method.name);
+                assert_eq!(
+                    decrypted.as_bytes(),
+                    plaintext.as_bytes(),
+                    "{}",
+                    method.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hyper_chaos_svd_refuses_deep_samples() {
+        let cipher = lookup("hyper-chaos-svd").unwrap().default_cipher();
+
+        for plaintext in [deep_image(8, 8), float_image_with_alpha(8, 8)] {
+            assert!(matches!(
+                cipher.encrypt(plaintext, &seed(1)),
+                Err(CipherError::UnsupportedColorType(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Chaotic orbits as random number generators.
+//!
+//! Every map the ciphers draw sequences from is an [`Orbit`], and [`ChaoticRng`] turns any
+//! of them into a [`rand_core`] generator. Permutation and diffusion stages take any such
+//! generator, and the streams can be tested with statistical suites or benchmarked on
+//! their own.
+//!
+//! These generators are meant for research into chaotic ciphers. None of them is a vetted
+//! CSPRNG, and the Arnold orbit is linear.
+
+use rand_core::{RngCore, SeedableRng, impls};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::Seed;
+use crate::kdf::SEED_LEN;
+
+pub use crate::arnold::CatOrbit;
+pub use crate::chen::HyperChen;
+pub use crate::henon::HenonOrbit;
+pub use crate::logistic::Logistic;
+pub use crate::onedim::{Map, MapOrbit};
+pub use crate::pwlcm::Pwlcm;
+pub use crate::tent::Tent;
+
+pub type LogisticRng = ChaoticRng<MapOrbit<Logistic>>;
+pub type TentRng = ChaoticRng<MapOrbit<Tent>>;
+pub type PwlcmRng = ChaoticRng<MapOrbit<Pwlcm>>;
+pub type HenonRng = ChaoticRng<HenonOrbit>;
+pub type ArnoldRng = ChaoticRng<CatOrbit>;
+pub type ChenRng = ChaoticRng<MapOrbit<HyperChen>>;
+
+/// Label of the orbits of generators made with [`SeedableRng::from_seed`].
+const RNG_LABEL: &str = "rng";
+
+/// A key-seeded chaotic orbit that yields 32 bits per step.
+pub trait Orbit {
+    /// Steps discarded by [`SeedableRng::from_seed`].
+    const TRANSIENT: u32;
+
+    /// Starts the orbit from the parameters `seed` gives for `label`, and discards
+    /// `transient` steps. Different labels give independent orbits.
+    fn new(seed: &Seed, label: &str, transient: u32) -> Self;
+
+    fn next_u32(&mut self) -> u32;
+}
+
+/// Steps between the checkpoints used to detect a periodic orbit.
+pub(crate) const CYCLE_WINDOW: usize = 1024;
+
+/// Detects a floating-point orbit that has fallen into a cycle or onto a fixed point, by
+/// comparing every value with a checkpoint taken every [`CYCLE_WINDOW`] steps.
+pub(crate) struct CycleCheck {
+    checkpoint: f64,
+    steps: usize,
+}
+
+impl CycleCheck {
+    /// Starts over from `value`, the current value of a freshly settled orbit.
+    pub(crate) fn new(value: f64) -> Self {
+        Self {
+            checkpoint: value,
+            steps: 0,
+        }
+    }
+
+    /// Whether `value`, the next value of the orbit, returns to the last checkpoint.
+    pub(crate) fn is_cycling(&mut self, value: f64) -> bool {
+        self.steps += 1;
+
+        if (value - self.checkpoint).abs() < 1e-10 {
+            return true;
+        }
+        if self.steps.is_multiple_of(CYCLE_WINDOW) {
+            self.checkpoint = value;
+        }
+        false
+    }
+}
+
+impl Drop for CycleCheck {
+    fn drop(&mut self) {
+        self.checkpoint.zeroize();
+    }
+}
+
+/// A [`rand_core`] generator over any chaotic [`Orbit`].
+pub struct ChaoticRng<O> {
+    orbit: O,
+}
+
+impl<O: Orbit> ChaoticRng<O> {
+    pub fn new(seed: &Seed, label: &str, transient: u32) -> Self {
+        Self {
+            orbit: O::new(seed, label, transient),
+        }
+    }
+}
+
+impl<O: Orbit> RngCore for ChaoticRng<O> {
+    fn next_u32(&mut self) -> u32 {
+        self.orbit.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst);
+    }
+}
+
+impl<O: Orbit> SeedableRng for ChaoticRng<O> {
+    type Seed = [u8; SEED_LEN];
+
+    /// Uses `seed` as if it were the output of the KDF.
+    fn from_seed(mut seed: Self::Seed) -> Self {
+        let rng = Self::new(&Seed::from_bytes(seed), RNG_LABEL, O::TRANSIENT);
+        seed.zeroize();
+        rng
+    }
+}
+
+/// One of the generators of this module, for settings that pick one by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    /// The logistic map.
+    Logistic,
+    /// The tent map.
+    Tent,
+    /// The piecewise linear chaotic map.
+    Pwlcm,
+    /// The Hénon map.
+    Henon,
+    /// The Arnold cat map, which is linear.
+    Arnold,
+    /// The hyperchaotic Chen system.
+    Chen,
+}
+
+impl Generator {
+    /// The generator for `label`, which discards its orbit's default transient.
+    pub fn rng(self, seed: &Seed, label: &str) -> Box<dyn RngCore> {
+        fn boxed<O: Orbit + 'static>(seed: &Seed, label: &str) -> Box<dyn RngCore> {
+            Box::new(ChaoticRng::<O>::new(seed, label, O::TRANSIENT))
+        }
+
+        match self {
+            Self::Logistic => boxed::<MapOrbit<Logistic>>(seed, label),
+            Self::Tent => boxed::<MapOrbit<Tent>>(seed, label),
+            Self::Pwlcm => boxed::<MapOrbit<Pwlcm>>(seed, label),
+            Self::Henon => boxed::<HenonOrbit>(seed, label),
+            Self::Arnold => boxed::<CatOrbit>(seed, label),
+            Self::Chen => boxed::<MapOrbit<HyperChen>>(seed, label),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{GENERATORS, seed};
+
+    fn bytes(mut rng: impl RngCore) -> [u8; 256] {
+        let mut bytes = [0; 256];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn from_seed_is_deterministic<O: Orbit>() {
+        let stream = |byte| bytes(ChaoticRng::<O>::from_seed([byte; SEED_LEN]));
+
+        assert_eq!(stream(1), stream(1));
+        assert_ne!(stream(1), stream(2));
+    }
+
+    #[test]
+    fn seeded_streams_are_deterministic() {
+        from_seed_is_deterministic::<MapOrbit<Logistic>>();
+        from_seed_is_deterministic::<MapOrbit<Tent>>();
+        from_seed_is_deterministic::<MapOrbit<Pwlcm>>();
+        from_seed_is_deterministic::<HenonOrbit>();
+        from_seed_is_deterministic::<CatOrbit>();
+        from_seed_is_deterministic::<MapOrbit<HyperChen>>();
+    }
+
+    #[test]
+    fn labels_give_different_streams() {
+        for generator in GENERATORS {
+            let stream = |label| bytes(generator.rng(&seed(1), label));
+
+            assert_eq!(stream("a"), stream("a"), "{generator:?}");
+            assert_ne!(stream("a"), stream("b"), "{generator:?}");
+        }
+    }
+}
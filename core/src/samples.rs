@@ -0,0 +1,155 @@
+//! Images as byte buffers of their native samples, which is what the ciphers work on.
+
+use image::{ColorType, DynamicImage, ImageBuffer, Pixel};
+
+use crate::{CipherError, check_dimensions};
+
+/// Width of the samples of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Depth {
+    U8,
+    U16,
+    F32,
+}
+
+impl Depth {
+    pub(crate) fn of(color_type: ColorType) -> Result<Depth, CipherError> {
+        match color_type {
+            ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => Ok(Depth::U8),
+            ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => {
+                Ok(Depth::U16)
+            }
+            ColorType::Rgb32F | ColorType::Rgba32F => Ok(Depth::F32),
+            color_type => Err(CipherError::UnsupportedColorType(color_type)),
+        }
+    }
+
+    /// Bytes per sample.
+    pub(crate) fn len(self) -> usize {
+        match self {
+            Depth::U8 => 1,
+            Depth::U16 => 2,
+            Depth::F32 => 4,
+        }
+    }
+}
+
+/// The samples of an image at its own depth and in its own channels, as bytes.
+///
+/// Grayscale images keep one or two channels and RGB images three, so only the channels
+/// that exist are encrypted. 16-bit samples are stored little-endian and `f32` samples as
+/// the little-endian bytes of their bit patterns, so byte-oriented ciphers see every bit of
+/// every sample and [`into_image`](Self::into_image) rebuilds the image exactly, whatever
+/// the bytes hold.
+pub(crate) struct Samples {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) color_type: ColorType,
+    pub(crate) depth: Depth,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Samples {
+    /// Takes the samples of `image`, which needs pixels and a supported color type.
+    pub(crate) fn new(image: DynamicImage) -> Result<Samples, CipherError> {
+        check_dimensions(&image)?;
+        let color_type = image.color();
+        let depth = Depth::of(color_type)?;
+        let (width, height) = (image.width(), image.height());
+
+        let bytes = match image {
+            DynamicImage::ImageRgb32F(buffer) => float_bytes(buffer.as_raw()),
+            DynamicImage::ImageRgba32F(buffer) => float_bytes(buffer.as_raw()),
+            image => match image.as_flat_samples_u16() {
+                Some(samples) => samples
+                    .samples
+                    .iter()
+                    .flat_map(|sample| sample.to_le_bytes())
+                    .collect(),
+                None => image.into_bytes(),
+            },
+        };
+
+        Ok(Samples {
+            width,
+            height,
+            color_type,
+            depth,
+            bytes,
+        })
+    }
+
+    /// Samples per pixel.
+    pub(crate) fn channels(&self) -> usize {
+        usize::from(self.color_type.channel_count())
+    }
+
+    /// Bytes per sample.
+    pub(crate) fn sample_len(&self) -> usize {
+        self.depth.len()
+    }
+
+    /// Bytes per pixel.
+    pub(crate) fn pixel_len(&self) -> usize {
+        self.channels() * self.sample_len()
+    }
+
+    pub(crate) fn into_image(self) -> DynamicImage {
+        let Samples {
+            width,
+            height,
+            color_type,
+            depth,
+            bytes,
+        } = self;
+
+        match depth {
+            Depth::U8 => match color_type {
+                ColorType::L8 => DynamicImage::ImageLuma8(buffer(width, height, bytes)),
+                ColorType::La8 => DynamicImage::ImageLumaA8(buffer(width, height, bytes)),
+                ColorType::Rgb8 => DynamicImage::ImageRgb8(buffer(width, height, bytes)),
+                _ => DynamicImage::ImageRgba8(buffer(width, height, bytes)),
+            },
+            Depth::U16 => {
+                let samples = bytes
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_le_bytes(bytes.try_into().expect("2 bytes")))
+                    .collect();
+                match color_type {
+                    ColorType::L16 => DynamicImage::ImageLuma16(buffer(width, height, samples)),
+                    ColorType::La16 => DynamicImage::ImageLumaA16(buffer(width, height, samples)),
+                    ColorType::Rgb16 => DynamicImage::ImageRgb16(buffer(width, height, samples)),
+                    _ => DynamicImage::ImageRgba16(buffer(width, height, samples)),
+                }
+            }
+            Depth::F32 => {
+                let samples = bytes
+                    .chunks_exact(4)
+                    .map(|bytes| {
+                        f32::from_bits(u32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+                    })
+                    .collect();
+                match color_type {
+                    ColorType::Rgb32F => DynamicImage::ImageRgb32F(buffer(width, height, samples)),
+                    _ => DynamicImage::ImageRgba32F(buffer(width, height, samples)),
+                }
+            }
+        }
+    }
+}
+
+fn float_bytes(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_bits().to_le_bytes())
+        .collect()
+}
+
+/// Wraps `samples`, which have to fill a `width x height` image.
+pub(crate) fn buffer<P: Pixel>(
+    width: u32,
+    height: u32,
+    samples: Vec<P::Subpixel>,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    ImageBuffer::from_raw(width, height, samples).expect("buffer matches the dimensions")
+}
@@ -0,0 +1,125 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::onedim::{self, Map};
+use crate::{CipherError, ImageCipher, Seed, registry};
+
+/// Tent map cipher, in the [permutation-diffusion scheme](crate::onedim) of the
+/// one-dimensional maps.
+///
+/// The orbits follow `x' = mu*min(x, 1 - x)`, with a key-derived `mu` in `[1.99, 2)`.
+/// `mu = 2` itself is avoided, since in binary floating point it shifts every orbit onto 0
+/// within about 50 steps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TentMap {
+    /// Iterations dropped before each orbit is used.
+    pub transient: u32,
+}
+
+impl Default for TentMap {
+    fn default() -> Self {
+        Self {
+            transient: onedim::DEFAULT_TRANSIENT,
+        }
+    }
+}
+
+/// Parameters and state of the map, see [`MapOrbit`](crate::rng::MapOrbit).
+pub struct Tent {
+    mu: f64,
+    x: f64,
+}
+
+impl Map for Tent {
+    const NAME: &'static str = "tent";
+
+    fn new(unit: impl Fn(&str) -> f64) -> Self {
+        Self {
+            // Rounding would take units just below 1 to 2 itself.
+            mu: (1.99 + 0.01 * unit("mu")).min(2.0f64.next_down()),
+            x: 0.01 + 0.98 * unit("x0"),
+        }
+    }
+
+    fn step(&mut self) -> f64 {
+        self.x = self.mu * self.x.min(1.0 - self.x);
+        self.x
+    }
+}
+
+impl Drop for Tent {
+    fn drop(&mut self) {
+        self.mu.zeroize();
+        self.x.zeroize();
+    }
+}
+
+impl ImageCipher for TentMap {
+    fn name(&self) -> &'static str {
+        "tent-map"
+    }
+
+    fn params(&self) -> serde_json::Value {
+        registry::to_params(self)
+    }
+
+    fn encrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::encrypt::<Tent>(image, seed, self.transient)
+    }
+
+    fn decrypt(&self, image: DynamicImage, seed: &Seed) -> Result<DynamicImage, CipherError> {
+        onedim::decrypt::<Tent>(image, seed, self.transient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onedim::MapOrbit;
+    use crate::rng::Orbit;
+    use crate::testing::seed;
+
+    /// Largest value below 1 that [`Seed::unit`] can return.
+    const UNIT_MAX: f64 = 1.0 - f64::EPSILON / 2.0;
+
+    fn drawn(byte: u8) -> Tent {
+        Tent::new(|name| seed(byte).unit(&format!("tent.{name}.0")))
+    }
+
+    #[test]
+    fn parameters_stay_in_the_chaotic_range() {
+        let maps = [Tent::new(|_| 0.0), Tent::new(|_| UNIT_MAX)]
+            .into_iter()
+            .chain((0..=255).map(drawn));
+
+        for map in maps {
+            assert!((1.99..2.0).contains(&map.mu), "mu = {}", map.mu);
+            assert!(map.x > 0.0 && map.x < 1.0);
+        }
+    }
+
+    #[test]
+    fn step_stays_in_the_unit_interval() {
+        for byte in 0..16 {
+            let mut map = drawn(byte);
+            for _ in 0..100_000 {
+                let x = map.step();
+                assert!((0.0..1.0).contains(&x), "x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_point_is_reseeded() {
+        let mut orbit = MapOrbit::<Tent>::new(&seed(1), "tent", Tent::TRANSIENT);
+        orbit.force(|map| {
+            map.x = map.mu / (1.0 + map.mu);
+            map.x
+        });
+
+        orbit.next_u32();
+        assert_eq!(orbit.attempt(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+//! Seeds, images and generators shared by the unit tests.
+
+use image::{DynamicImage, ImageBuffer, Rgb, RgbImage, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::Seed;
+use crate::kdf::SEED_LEN;
+use crate::rng::Generator;
+
+/// Every [`Generator`].
+pub(crate) const GENERATORS: [Generator; 6] = [
+    Generator::Logistic,
+    Generator::Tent,
+    Generator::Pwlcm,
+    Generator::Henon,
+    Generator::Arnold,
+    Generator::Chen,
+];
+
+/// A fixed seed, without going through the KDF.
+pub(crate) fn seed(byte: u8) -> Seed {
+    Seed::from_bytes([byte; SEED_LEN])
+}
+
+/// An RGB image whose pixels all differ, as long as both sides are at most 256.
+pub(crate) fn image(width: u32, height: u32) -> DynamicImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        Rgb([x as u8, y as u8, (x * 7 + y * 13) as u8])
+    })
+    .into()
+}
+
+/// Like [`image`], with an alpha channel that varies across the image.
+pub(crate) fn image_with_alpha(width: u32, height: u32) -> DynamicImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([
+            x as u8,
+            y as u8,
+            (x * 7 + y * 13) as u8,
+            (x * 31 + y * 3) as u8,
+        ])
+    })
+    .into()
+}
+
+/// An RGB image with 16-bit samples that use both bytes.
+pub(crate) fn deep_image(width: u32, height: u32) -> DynamicImage {
+    ImageBuffer::<Rgb<u16>, _>::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 2741 + y) as u16,
+            (y * 4093 + x) as u16,
+            (x * 7 + y * 13) as u16 ^ 0xa5a5,
+        ])
+    })
+    .into()
+}
+
+/// An RGBA image with `f32` samples, among them NaNs with payloads, infinities, negative
+/// zero and subnormals, whose bit patterns a cipher has to carry over exactly.
+pub(crate) fn float_image_with_alpha(width: u32, height: u32) -> DynamicImage {
+    const SPECIAL: [u32; 8] = [
+        0x7fc0_0000,
+        0x7f80_0001,
+        0xffff_ffff,
+        0x7f80_0000,
+        0xff80_0000,
+        0x8000_0000,
+        0x0000_0001,
+        0x3f80_0000,
+    ];
+
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        let index = (y * width + x) as usize;
+        Rgba([
+            f32::from_bits(SPECIAL[index % SPECIAL.len()]),
+            x as f32 / width as f32,
+            -(y as f32) * 1.5,
+            f32::from_bits(0x7fc0_0000 | index as u32),
+        ])
+    })
+    .into()
+}
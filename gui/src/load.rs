@@ -1,11 +1,16 @@
-use std::{io::Cursor, path::Path, sync::Arc};
+use std::{
+    io::{self, Cursor},
+    path::Path,
+    sync::Arc,
+};
 
-use image::{io::Reader, DynamicImage};
+use image::ImageReader;
 use rfd::FileHandle;
+use tokio::io::AsyncWriteExt;
 
-use crate::Error;
+use crate::{Error, ImageData, TabId};
 
-pub async fn pick_and_load_images() -> Result<Arc<Vec<DynamicImage>>, Error> {
+pub async fn pick_and_load_images(image_type: TabId) -> Result<Arc<Vec<ImageData>>, Error> {
     let files = pick_files().await?;
 
     load_images(
@@ -14,24 +19,33 @@ pub async fn pick_and_load_images() -> Result<Arc<Vec<DynamicImage>>, Error> {
             .iter()
             .map(|file_handle| file_handle.path())
             .collect(),
+        image_type,
     )
     .await
 }
 
-async fn load_images(paths: Vec<impl AsRef<Path>>) -> Result<Arc<Vec<DynamicImage>>, Error> {
+async fn load_images(
+    paths: Vec<impl AsRef<Path>>,
+    image_type: TabId,
+) -> Result<Arc<Vec<ImageData>>, Error> {
     let mut images = Vec::new();
     for path in paths {
-        let raw = tokio::fs::read(path)
+        let raw = tokio::fs::read(&path)
             .await
             .map_err(|err| Error::IO(err.kind()))?;
 
-        let image = Reader::new(Cursor::new(raw))
+        let image = ImageReader::new(Cursor::new(&raw))
             .with_guessed_format()
             .map_err(|err| Error::IO(err.kind()))?
             .decode()
             .map_err(|_| Error::ImageDecode)?;
 
-        images.push(image);
+        images.push(ImageData {
+            image_type,
+            path: path.as_ref().to_path_buf(),
+            data: image,
+            bytes: Arc::new(raw),
+        });
     }
 
     Ok(Arc::new(images))
@@ -46,3 +60,44 @@ async fn pick_files() -> Result<Arc<Vec<FileHandle>>, Error> {
         .map(Arc::new)
         .ok_or(Error::DialogClosed)
 }
+
+/// Writes every result into a folder picked by the user, under its source file name.
+///
+/// Existing files are never replaced: a ciphertext saved next to its plaintext would
+/// otherwise overwrite it when both are PNGs. Nothing is written if any name is taken.
+pub async fn pick_and_save_images(images: Arc<Vec<ImageData>>) -> Result<(), Error> {
+    let folder = rfd::AsyncFileDialog::new()
+        .set_title("Save results")
+        .pick_folder()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    let mut paths = Vec::with_capacity(images.len());
+    for image in images.iter() {
+        let path = folder
+            .path()
+            .join(image.path.file_name().ok_or(Error::Validation)?);
+        if tokio::fs::try_exists(&path)
+            .await
+            .map_err(|err| Error::IO(err.kind()))?
+        {
+            return Err(Error::IO(io::ErrorKind::AlreadyExists));
+        }
+        paths.push(path);
+    }
+
+    for (image, path) in images.iter().zip(paths) {
+        // create_new also catches a file that appeared since the check.
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .await
+            .map_err(|err| Error::IO(err.kind()))?;
+        file.write_all(image.bytes.as_slice())
+            .await
+            .map_err(|err| Error::IO(err.kind()))?;
+    }
+
+    Ok(())
+}
@@ -1,17 +1,20 @@
+use core::CipherError;
+use core::registry::{self, MethodInfo};
 use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
 use std::io;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
 
 use iced::alignment::{Horizontal, Vertical};
 use iced::font::{self, Font};
 use iced::widget::image::Handle;
-use iced::widget::{button, column, container, row, text, Image, Row, Space, Text, TextInput};
-use iced::{executor, Alignment};
+use iced::widget::{Image, Row, Text, TextInput, button, column, container, text};
+use iced::{Alignment, executor};
 use iced::{Application, Command, Element, Length, Settings, Theme};
 use iced_aw::{SelectionList, SelectionListStyles, Spinner, TabBar, TabBarStyles, TabLabel};
 use image::DynamicImage;
-use load::pick_and_load_images;
-use manipulate::{ArnoldCat, EncMethod, Henon, ImageEncyptor};
+use load::{pick_and_load_images, pick_and_save_images};
 
 mod load;
 mod manipulate;
@@ -28,14 +31,25 @@ pub enum Error {
     ImageDecode,
     IO(io::ErrorKind),
     Validation,
+    Cipher(Arc<CipherError>),
+}
+
+impl From<CipherError> for Error {
+    fn from(err: CipherError) -> Self {
+        Error::Cipher(Arc::new(err))
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let literal = match self {
-            Error::DialogClosed => "Dialog closed",
-            Error::ImageDecode => "Image decode failed",
-            _ => "Error",
+            Error::DialogClosed => "Dialog closed".to_string(),
+            Error::ImageDecode => "Image decode failed".to_string(),
+            Error::Cipher(err) => err.to_string(),
+            Error::IO(io::ErrorKind::AlreadyExists) => {
+                "A file with that name already exists in the folder".to_string()
+            }
+            _ => "Error".to_string(),
         };
 
         write!(f, "Error | {}", literal)
@@ -49,10 +63,40 @@ enum TabId {
     Decrypt,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct ImageData {
     image_type: TabId,
+    path: PathBuf,
     data: DynamicImage,
+    /// Encoded file, as it was loaded or as it will be saved.
+    bytes: Arc<Vec<u8>>,
+}
+
+/// A method from the core registry, listed by its title.
+#[derive(Debug, Clone, Copy)]
+struct Method(&'static MethodInfo);
+
+static METHODS: LazyLock<Vec<Method>> =
+    LazyLock::new(|| registry::methods().iter().map(Method).collect());
+
+impl PartialEq for Method {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.name == other.0.name
+    }
+}
+
+impl Eq for Method {}
+
+impl Hash for Method {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.name.hash(state);
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.title)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -60,22 +104,24 @@ enum Message {
     FontLoaded(Result<(), font::Error>),
     TabSelected(TabId),
     OpenFileDialog,
-    FilesOpened(Result<Arc<Vec<DynamicImage>>, Error>),
-    EncMethodSelected(usize, EncMethod),
+    FilesOpened(Result<Arc<Vec<ImageData>>, Error>),
+    EncMethodSelected(Method),
     PwdFieldEdited(String),
     EncryptBtnPressed,
+    Processed(Result<Arc<Vec<ImageData>>, Error>),
+    SaveBtnPressed,
+    Saved(Result<(), Error>),
 }
 
 #[derive()]
 struct Imaged {
     tab_index: TabId,
     images: Option<Vec<ImageData>>,
-    res_images: Option<Vec<ImageData>>,
+    res_images: Option<Arc<Vec<ImageData>>>,
     error: Option<Error>,
     password: String,
-    enc_method_state: Option<EncMethod>,
+    enc_method_state: Option<Method>,
     loading: bool,
-    // enc_method_state: State<EncMethod>,
 }
 
 impl Default for Imaged {
@@ -88,7 +134,6 @@ impl Default for Imaged {
             password: "".to_string(),
             enc_method_state: None,
             loading: false,
-            // enc_method_state: State::new(Vec::new()),
         }
     }
 }
@@ -115,7 +160,7 @@ impl Application for Imaged {
     fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
         (
             Self::default(),
-            font::load(include_bytes!("../assets/fonts/icons.ttf").as_slice())
+            font::load(include_bytes!("../../assets/fonts/icons.ttf").as_slice())
                 .map(Message::FontLoaded),
         )
     }
@@ -136,67 +181,71 @@ impl Application for Imaged {
                 Command::none()
             }
             Message::OpenFileDialog => {
-                Command::perform(pick_and_load_images(), Message::FilesOpened)
+                Command::perform(pick_and_load_images(self.tab_index), Message::FilesOpened)
             }
             Message::FilesOpened(images_res) => {
                 match images_res {
-                    Ok(images) => {
-                        self.images = Some(
-                            images
-                                .iter()
-                                .map(|image| ImageData {
-                                    image_type: self.tab_index,
-                                    data: image.clone(),
-                                })
-                                .collect(),
-                        )
-                    }
+                    Ok(images) => self.images = Some(images.to_vec()),
                     Err(e) => self.error = Some(e),
                 }
                 Command::none()
             }
-            Message::EncMethodSelected(_, val) => {
+            Message::EncMethodSelected(val) => {
                 self.enc_method_state = Some(val);
-                self.password = "".to_string();
                 Command::none()
             }
             Message::PwdFieldEdited(val) => {
-                self.password = val.clone();
-                self.enc_method_state =
-                    self.enc_method_state.as_ref().map(|method| match &method {
-                        EncMethod::ArnoldCat(_) => {
-                            EncMethod::ArnoldCat(Some(ArnoldCat { key: val }))
-                        }
-                        EncMethod::Henon(_) => EncMethod::Henon(Some(Henon { key: val })),
-                    });
+                self.password = val;
                 Command::none()
             }
             Message::EncryptBtnPressed => {
-                if !self.password.is_empty() {
-                    if let Some(method) = &self.enc_method_state {
-                        self.loading = true;
-
-                        let images = self.get_enc_variant_items().clone().unwrap().clone();
-                        let res = method
-                            .encrypt(images.into_iter().map(|val| val.data.clone()).collect())
-                            .into_iter()
-                            .map(|res| ImageData {
-                                data: res,
-                                image_type: self.tab_index,
-                            })
-                            .collect();
-                        self.res_images = Some(res);
-
-                        self.loading = false;
-                    }
+                let Some(images) = self.get_enc_variant_items() else {
+                    return Command::none();
+                };
+                if self.password.is_empty() {
+                    return Command::none();
                 }
+                let images: Vec<ImageData> = images.into_iter().cloned().collect();
+                let key = self.password.clone();
 
+                // Decryption takes the method from the file header.
+                let task = match (self.tab_index, self.enc_method_state) {
+                    (TabId::Encrypt, Some(method)) => Command::perform(
+                        manipulate::encrypt(images, method.0, key),
+                        Message::Processed,
+                    ),
+                    (TabId::Encrypt, None) => return Command::none(),
+                    (TabId::Decrypt, _) => {
+                        Command::perform(manipulate::decrypt(images, key), Message::Processed)
+                    }
+                };
+
+                self.loading = true;
+                self.error = None;
+                task
+            }
+            Message::Processed(res) => {
+                self.loading = false;
+                match res {
+                    Ok(images) => self.res_images = Some(images),
+                    Err(e) => self.error = Some(e),
+                }
+                Command::none()
+            }
+            Message::SaveBtnPressed => match &self.res_images {
+                Some(images) => {
+                    Command::perform(pick_and_save_images(images.clone()), Message::Saved)
+                }
+                None => Command::none(),
+            },
+            Message::Saved(res) => {
+                self.error = res.err();
                 Command::none()
             }
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let tab_bar = TabBar::new(Message::TabSelected)
             .push(TabId::Encrypt, TabLabel::Text(String::from("Encrypt")))
             .push(TabId::Decrypt, TabLabel::Text(String::from("Decrypt")))
@@ -210,28 +259,37 @@ impl Application for Imaged {
                 TabId::Decrypt => "Decrypt",
             })
             .on_press_maybe(
-                if self.enc_method_state.is_none()
+                if (self.tab_index == TabId::Encrypt && self.enc_method_state.is_none())
                 || self.get_enc_variant_items().is_none()  // Ensure images of particular variant present
                 || self.password.is_empty()
+                || self.loading
                 {
                     None
                 } else {
                     Some(Message::EncryptBtnPressed)
                 },
             );
-            Row::new().push(pick_file_btn).push(encrypt_btn).spacing(10)
+            let save_btn = button("Save results").on_press_maybe(
+                self.res_images
+                    .as_ref()
+                    .filter(|images| {
+                        images
+                            .iter()
+                            .any(|image| image.image_type == self.tab_index)
+                    })
+                    .map(|_| Message::SaveBtnPressed),
+            );
+            Row::new()
+                .push(pick_file_btn)
+                .push(encrypt_btn)
+                .push(save_btn)
+                .spacing(10)
         };
 
-        // let select_enc_method = ComboBox::new(
-        //     &self.enc_method_state,
-        //     "hi",
-        //     Some(&EncMethod::ArnoldCat),
-        //     Message::EncMethodSelected,
-        // );
         let input_bar = {
             let select_enc_method = SelectionList::new_with(
-                &[EncMethod::ArnoldCat(None), EncMethod::Henon(None)],
-                Message::EncMethodSelected,
+                &METHODS,
+                |_, method| Message::EncMethodSelected(method),
                 16_f32,
                 6_f32,
                 SelectionListStyles::Default,
@@ -274,14 +332,13 @@ impl Application for Imaged {
                     }
                 }
 
-                // Scrollable::new(row)
                 row.align_items(Alignment::Center).spacing(5).height(250)
             };
             let res_image_view = {
                 let mut row: Row<'_, Message> = Row::new();
                 match &self.res_images {
                     Some(images) => {
-                        for image in images {
+                        for image in images.iter() {
                             if self.tab_index == image.image_type {
                                 let bytes = image.data.clone();
                                 let image = Image::new(Handle::from_pixels(
@@ -300,7 +357,6 @@ impl Application for Imaged {
                     }
                 }
 
-                // Scrollable::new(row)
                 row.align_items(Alignment::Center).spacing(5).height(250)
             };
             container(column![image_view, res_image_view].align_items(Alignment::Center))
@@ -313,7 +369,6 @@ impl Application for Imaged {
         let spinner: Element<Message> = if self.loading {
             Spinner::new().into()
         } else {
-            // Space::new(Length::Shrink, Length::Fixed(5_f32)).into()
             Text::new(format!("{}", self.loading)).into()
         };
 
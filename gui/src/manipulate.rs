@@ -1,116 +1,86 @@
-use std::fmt::Display;
-
-use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ArnoldCat {
-    pub key: String,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Henon {
-    pub key: String,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum EncMethod {
-    ArnoldCat(Option<ArnoldCat>),
-    Henon(Option<Henon>),
+use core::container::AlphaMode;
+use core::registry::MethodInfo;
+use core::{CipherError, NonceMode, container};
+use std::io::{self, Cursor};
+use std::sync::Arc;
+
+use image::ImageFormat;
+use zeroize::Zeroizing;
+
+use crate::{Error, ImageData, TabId};
+
+/// Encrypts every image into an imaged container, the same file the CLI writes.
+pub async fn encrypt(
+    images: Vec<ImageData>,
+    method: &'static MethodInfo,
+    key: String,
+) -> Result<Arc<Vec<ImageData>>, Error> {
+    let key = Zeroizing::new(key);
+    run(move || {
+        let cipher = method.default_cipher();
+
+        images
+            .into_iter()
+            .map(|image| {
+                let format = ImageFormat::from_path(&image.path).ok();
+                let bytes = container::encrypt(
+                    image.data,
+                    format,
+                    cipher.as_ref(),
+                    &key,
+                    NonceMode::default(),
+                    AlphaMode::default(),
+                )?;
+
+                Ok(ImageData {
+                    image_type: TabId::Encrypt,
+                    path: image.path.with_extension("png"),
+                    data: image::load_from_memory_with_format(&bytes, ImageFormat::Png)?,
+                    bytes: Arc::new(bytes),
+                })
+            })
+            .collect()
+    })
+    .await
 }
 
-impl Display for EncMethod {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                EncMethod::ArnoldCat(_) => "ArnoldCat",
-                EncMethod::Henon(_) => "Hénon",
-            }
-        )
-    }
+/// Decrypts imaged containers with the method recorded in their headers, restoring the
+/// format of the source file.
+pub async fn decrypt(images: Vec<ImageData>, key: String) -> Result<Arc<Vec<ImageData>>, Error> {
+    let key = Zeroizing::new(key);
+    run(move || {
+        images
+            .into_iter()
+            .map(|image| {
+                let (data, header) = container::decrypt(&image.bytes, &key)?;
+                let format = header
+                    .source_format
+                    .as_deref()
+                    .and_then(ImageFormat::from_extension)
+                    .unwrap_or(ImageFormat::Png);
+
+                let mut bytes = Vec::new();
+                data.write_to(&mut Cursor::new(&mut bytes), format)?;
+
+                Ok(ImageData {
+                    image_type: TabId::Decrypt,
+                    path: image.path.with_extension(format.extensions_str()[0]),
+                    data,
+                    bytes: Arc::new(bytes),
+                })
+            })
+            .collect()
+    })
+    .await
 }
 
-impl Default for EncMethod {
-    fn default() -> Self {
-        Self::ArnoldCat(None)
-    }
-}
-
-impl EncMethod {
-    pub fn encrypt(&self, images: Vec<DynamicImage>) -> Vec<DynamicImage> {
-        let res: Vec<DynamicImage> = match self {
-            EncMethod::ArnoldCat(enc) => images
-                .into_iter()
-                .map(|image| enc.clone().unwrap().encrypt(image))
-                .collect(),
-            EncMethod::Henon(enc) => images
-                .into_iter()
-                .map(|image| enc.clone().unwrap().encrypt(image))
-                .collect(),
-        };
-        res
-    }
-
-    pub fn decrypt(&self, images: Vec<DynamicImage>) -> Vec<DynamicImage> {
-        let res: Vec<DynamicImage> = match self {
-            EncMethod::ArnoldCat(enc) => images
-                .into_iter()
-                .map(|image| enc.clone().unwrap().decrypt(image))
-                .collect(),
-            EncMethod::Henon(enc) => images
-                .into_iter()
-                .map(|image| enc.clone().unwrap().decrypt(image))
-                .collect(),
-        };
-        res
-    }
-}
-
-pub trait ImageEncyptor {
-    fn encrypt(&self, image: DynamicImage) -> DynamicImage;
-    fn decrypt(&self, image: DynamicImage) -> DynamicImage;
-}
-
-impl ImageEncyptor for ArnoldCat {
-    fn encrypt(&self, image: DynamicImage) -> DynamicImage {
-        let mut buffer = RgbaImage::new(image.width(), image.height());
-        let mut rgba_image = image.to_rgba8();
-
-        let width = rgba_image.width();
-        let height = rgba_image.height();
-        let n = width.min(height);
-
-        for _ in 0..10 {
-            for x in 0..width {
-                for y in 0..height {
-                    let nx = (x * y) % n;
-                    let ny = (x + 2 * y) % n;
-
-                    buffer.put_pixel(x, y, rgba_image.get_pixel(nx, ny).clone());
-                }
-            }
-
-            rgba_image = buffer.clone();
-        }
-
-        buffer.into()
-    }
-
-    fn decrypt(&self, image: DynamicImage) -> DynamicImage {
-        let mut buffer = RgbaImage::new(image.width(), image.height());
-        let mut rgba_image = image.to_rgba8();
-
-        image
-    }
-}
-
-impl ImageEncyptor for Henon {
-    fn encrypt(&self, image: DynamicImage) -> DynamicImage {
-        image
-    }
-
-    fn decrypt(&self, image: DynamicImage) -> DynamicImage {
-        image
-    }
+/// Runs the CPU bound cipher work off the UI thread.
+async fn run(
+    work: impl FnOnce() -> Result<Vec<ImageData>, CipherError> + Send + 'static,
+) -> Result<Arc<Vec<ImageData>>, Error> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|_| Error::IO(io::ErrorKind::Interrupted))?
+        .map(Arc::new)
+        .map_err(Error::from)
 }
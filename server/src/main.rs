@@ -1,13 +1,109 @@
-use poem::{Route, Server, get, handler, listener::TcpListener, web::Path};
+use core::{CipherError, KdfParams, container, registry};
+use std::io::Cursor;
 
+use image::ImageFormat;
+use poem::http::StatusCode;
+use poem::middleware::SizeLimit;
+use poem::web::Json;
+use poem::{
+    EndpointExt, Request, Response, Route, Server, get, handler, listener::TcpListener, post,
+};
+use tokio::sync::Semaphore;
+use zeroize::Zeroizing;
+
+/// Header that carries the key, so it stays out of URLs and access logs.
+const KEY_HEADER: &str = "X-Imaged-Key";
+/// Largest request body accepted, in bytes.
+const MAX_BODY_LEN: usize = 64 << 20;
+/// Largest KDF memory cost in KiB this server derives with, well below the limit of the
+/// container but above the default costs everything is encrypted with.
+const MAX_MEMORY_KIB: u32 = 64 * 1024;
+const MAX_ITERATIONS: u32 = 8;
+const MAX_PARALLELISM: u32 = 4;
+/// Decryptions that may run at once. The header is only authenticated after the key has
+/// been derived, so each request can cost up to [`MAX_MEMORY_KIB`] before it fails.
+const MAX_DECRYPTIONS: usize = 4;
+
+static DECRYPTIONS: Semaphore = Semaphore::const_new(MAX_DECRYPTIONS);
+
+#[handler]
+async fn index() -> String {
+    "Server running".to_string()
+}
+
+/// Lists the available methods and their parameters.
 #[handler]
-async fn index(Path(name): Path<String>) -> String {
-    format!("Server running")
+async fn methods() -> Json<&'static [registry::MethodInfo]> {
+    Json(registry::methods())
+}
+
+/// Decrypts an imaged PNG sent as the request body and responds with the plain image as PNG.
+#[handler]
+async fn decrypt(req: &Request, body: Vec<u8>) -> poem::Result<Response> {
+    let key = Zeroizing::new(
+        req.header(KEY_HEADER)
+            .ok_or_else(|| {
+                poem::Error::from_string(
+                    format!("Missing {KEY_HEADER} header"),
+                    StatusCode::BAD_REQUEST,
+                )
+            })?
+            .to_string(),
+    );
+
+    check_kdf(&container::read_header(&body).map_err(error_response)?.kdf)
+        .map_err(error_response)?;
+    let permit = DECRYPTIONS
+        .acquire()
+        .await
+        .map_err(poem::error::InternalServerError)?;
+
+    let png = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, CipherError> {
+        let _permit = permit;
+        let (image, _) = container::decrypt(&body, &key)?;
+
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    })
+    .await
+    .map_err(poem::error::InternalServerError)?
+    .map_err(error_response)?;
+
+    Ok(Response::builder().content_type("image/png").body(png))
+}
+
+/// Rejects KDF costs beyond the limits of this server, before anything is derived.
+fn check_kdf(kdf: &KdfParams) -> Result<(), CipherError> {
+    if kdf.memory_kib > MAX_MEMORY_KIB
+        || kdf.iterations > MAX_ITERATIONS
+        || kdf.parallelism > MAX_PARALLELISM
+    {
+        return Err(CipherError::InvalidHeader(format!(
+            "KDF costs above {MAX_MEMORY_KIB} KiB, {MAX_ITERATIONS} iterations or \
+             {MAX_PARALLELISM} lanes are not accepted here"
+        )));
+    }
+    Ok(())
+}
+
+fn error_response(err: CipherError) -> poem::Error {
+    let status = match err {
+        CipherError::WrongKey => StatusCode::UNAUTHORIZED,
+        CipherError::Tampered => StatusCode::UNPROCESSABLE_ENTITY,
+        CipherError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    poem::Error::from_string(err.to_string(), status)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
-    let app = Route::new().at("/", get(index));
+    let app = Route::new()
+        .at("/", get(index))
+        .at("/methods", get(methods))
+        .at("/decrypt", post(decrypt).with(SizeLimit::new(MAX_BODY_LEN)));
 
     println!("Serving at http://localhost:3000");
     Server::new(TcpListener::bind("0.0.0.0:3000"))